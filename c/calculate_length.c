@@ -0,0 +1,31 @@
+// Project: references
+// Author: Greg Folker
+
+// The C version of `calculate_length` from the `borrowing` lesson. The
+// `const` says the function will not change the string, but unlike a Rust
+// `&String` it says nothing about who owns it: the function could just as
+// well free it, and the compiler would not object
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static size_t calculate_length(const char *s) {
+    return strlen(s);
+}
+
+int main(void) {
+    char *s1 = malloc(32);
+    if (s1 == NULL) {
+        return 1;
+    }
+    strcpy(s1, "Hello");
+
+    size_t len = calculate_length(s1);
+
+    // `s1` can still be used here, but only because `calculate_length`
+    // happened not to free it, not because anything checked
+    printf("The length of '%s' is %zu\n", s1, len);
+    free(s1);
+    return 0;
+}
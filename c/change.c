@@ -0,0 +1,34 @@
+// Project: references
+// Author: Greg Folker
+
+// The C version of `change` from the `mutable-references` lesson. C has no
+// references: everything is passed by value, and a function changes its
+// caller's data by being handed a pointer to it. Nothing at the call site
+// says the data may change, and nothing stops a second pointer to the same
+// buffer being changed at the same time
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+// Appends ", world" to a buffer that has to be big enough for it, which
+// only the comment promises
+static void change(char *some_string) {
+    strcat(some_string, ", world");
+}
+
+int main(void) {
+    char *s = malloc(32);
+    if (s == NULL) {
+        return 1;
+    }
+    strcpy(s, "Hello");
+
+    // Compare `change(&mut s)` in Rust: here the call looks no different
+    // from one that only reads `s`
+    change(s);
+
+    printf("%s\n", s);
+    free(s);
+    return 0;
+}
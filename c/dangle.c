@@ -0,0 +1,34 @@
+// Project: references
+// Author: Greg Folker
+
+// The C version of `dangle` from the `dangling-references` lesson. The
+// string is freed when the function ends, just as a Rust `String` is
+// dropped at the end of its scope, but the pointer to it is returned
+// anyway. The C compiler accepts this, with a warning at most, and reading
+// through the pointer is undefined behaviour: with AddressSanitizer the
+// program is stopped with a heap-use-after-free report, and without it the
+// program prints whatever is in the freed memory, or crashes
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static char *dangle(void) {
+    char *s = malloc(32);
+    if (s == NULL) {
+        exit(1);
+    }
+    strcpy(s, "Hello");
+
+    // The equivalent of `s` being dropped as it goes out of scope
+    free(s);
+
+    return s;
+}
+
+int main(void) {
+    char *reference_to_nothing = dangle();
+
+    printf("reference_to_nothing=%s\n", reference_to_nothing);
+    return 0;
+}
@@ -0,0 +1,224 @@
+// Project: references
+// Author: Greg Folker
+
+// Named places in the lesson files that commentary can point at instead of
+// hard-coding a line number that goes stale as soon as a line is added
+//
+// A line is anchored with a trailing comment:
+//
+//     println!("r1={} and r2={}", r1, r2); // @anchor last-shared-use
+//
+// and referred to as `{@last-shared-use}`, which reads as the line number
+// once resolved. The first line of code after a `@compile_fail` marker is
+// anchored under the name of its example as well
+
+use crate::lesson;
+use crate::source::{self, Error};
+
+const ANCHOR: &str = "@anchor";
+
+/// Anchor names and the lines they are on, counting from 1
+pub struct Anchors {
+    lines: Vec<(String, usize)>,
+}
+
+impl Anchors {
+    pub fn line(&self, name: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|&(_, line)| line)
+    }
+}
+
+pub fn collect(file: &'static str, text: &str) -> Result<Anchors, Error> {
+    let mut anchors = Anchors { lines: Vec::new() };
+
+    for (i, line) in text.lines().enumerate() {
+        let comment = match line.find("//") {
+            Some(start) => &line[start + 2..],
+            None => continue,
+        };
+
+        let found = if let Some(at) = comment.find(ANCHOR) {
+            comment[at + ANCHOR.len()..]
+                .split_whitespace()
+                .next()
+                .map(|name| (name, i + 1))
+        } else if let Some(rest) = comment.trim().strip_prefix("@compile_fail") {
+            rest.split_whitespace().next().map(|name| (name, i + 2))
+        } else {
+            continue;
+        };
+
+        let (name, line) = match found {
+            Some(found) => found,
+            None => {
+                return Err(Error {
+                    file,
+                    line: i + 1,
+                    message: String::from("anchor is missing a name"),
+                })
+            }
+        };
+
+        match anchors.line(name) {
+            // A `@compile_fail` example may be split over several blocks,
+            // in which case the first block is the one that is pointed at
+            Some(_) if !comment.contains(ANCHOR) => {}
+            Some(other) => {
+                return Err(Error {
+                    file,
+                    line: i + 1,
+                    message: format!("anchor '{}' is already defined on line {}", name, other),
+                })
+            }
+            None => anchors.lines.push((name.to_string(), line)),
+        }
+    }
+
+    Ok(anchors)
+}
+
+/// Replaces every `{@name}` in `text` with the line `name` is anchored to
+/// and removes the `// @anchor` comments. Only the contents of lines
+/// change, so line numbers are the same before and after
+pub fn resolve(file: &'static str, text: &str, anchors: &Anchors) -> Result<String, Error> {
+    let mut resolved = String::with_capacity(text.len());
+
+    for (i, line) in text.lines().enumerate() {
+        let line = match line.find("// @anchor") {
+            Some(start) => line[..start].trim_end(),
+            None => line,
+        };
+
+        resolved.push_str(&references(line, |name| {
+            anchors.line(name).ok_or_else(|| Error {
+                file,
+                line: i + 1,
+                message: format!("there is no anchor named '{}'", name),
+            })
+        })?);
+        resolved.push('\n');
+    }
+
+    Ok(resolved)
+}
+
+/// Resolves the anchors in `text` against `text` itself, as it is once
+/// the lines `removed` picks out have been taken out of it
+pub fn resolve_within<F>(file: &'static str, text: &str, removed: F) -> Result<String, Error>
+where
+    F: Fn(&str) -> bool,
+{
+    let lines: Vec<&str> = text.lines().collect();
+    let collected = collect(file, text)?;
+    let anchors = Anchors {
+        lines: collected
+            .lines
+            .into_iter()
+            .map(|(name, line)| {
+                let before = &lines[..(line - 1).min(lines.len())];
+                let gone = before.iter().filter(|line| removed(line)).count();
+                (name, line - gone)
+            })
+            .collect(),
+    };
+
+    let kept: Vec<&str> = lines.into_iter().filter(|line| !removed(line)).collect();
+    resolve(file, &(kept.join("\n") + "\n"), &anchors)
+}
+
+/// Checks every lesson file and every lesson's narration, returning
+/// everything that would fail to resolve
+pub fn check() -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    for (file, text) in source::SOURCES {
+        let anchors = match collect(file, text) {
+            Ok(anchors) => anchors,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            if let Err(e) = resolve(file, line, &anchors) {
+                errors.push(Error { line: i + 1, ..e });
+            }
+            if let Some(number) = hard_coded_line(line) {
+                errors.push(Error {
+                    file,
+                    line: i + 1,
+                    message: format!("'{}' is hard-coded, refer to an anchor instead", number),
+                });
+            }
+        }
+    }
+
+    for lesson in lesson::all() {
+        if let Err(e) = lesson.resolved_narration() {
+            errors.push(e);
+        }
+    }
+
+    errors
+}
+
+// Calls `line_of` for every `{@name}` in `text`, putting the number it
+// returns in its place
+fn references<F>(text: &str, mut line_of: F) -> Result<String, Error>
+where
+    F: FnMut(&str) -> Result<usize, Error>,
+{
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{@") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        resolved.push_str(&rest[..start]);
+        resolved.push_str(&line_of(&rest[start + 2..end])?.to_string());
+        rest = &rest[end + 1..];
+    }
+
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Resolves the anchors in a piece of text that lives outside of `file`,
+/// such as a lesson's narration, against the anchors in `file`
+pub fn resolve_in(file: &'static str, text: &str) -> Result<String, Error> {
+    let source = source::SOURCES
+        .iter()
+        .find(|(path, _)| *path == file)
+        .map(|(_, source)| *source)
+        .unwrap_or("");
+    let anchors = collect(file, source)?;
+
+    references(text, |name| {
+        anchors.line(name).ok_or_else(|| Error {
+            file,
+            line: 0,
+            message: format!("there is no anchor named '{}'", name),
+        })
+    })
+}
+
+// Comments such as "Line 68 is a compiler error" that name a line outright
+fn hard_coded_line(line: &str) -> Option<&str> {
+    let comment = &line[line.find("//")? + 2..];
+    let at = comment.find("Line ").or_else(|| comment.find("line "))?;
+    let rest = &comment[at + 5..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    if digits > 0 {
+        Some(&comment[at..at + 5 + digits])
+    } else {
+        None
+    }
+}
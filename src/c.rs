@@ -0,0 +1,180 @@
+// Project: references
+// Author: Greg Folker
+
+// The C programs in `c/` that do what some of the lesson functions do, so
+// that what C allows can be shown next to what Rust does. They are
+// compiled with the local C compiler, with AddressSanitizer if it has it,
+// and run in the program sandbox like a student's code. Nothing about
+// what they print is written down here: a dangling pointer may print
+// garbage, crash, or be caught by the sanitizer, and whichever happens is
+// what gets shown
+
+use std::env;
+use std::fs;
+use std::io;
+
+use crate::sandbox::{self, Limits, Run, Sandbox, NO_ARGS};
+use crate::tempdir::TempDir;
+
+/// Flags every program is compiled with
+pub const FLAGS: &[&str] = &["-std=c99", "-Wall", "-g"];
+
+/// Added to `FLAGS` when the compiler supports AddressSanitizer
+pub const SANITIZER_FLAG: &str = "-fsanitize=address";
+
+// AddressSanitizer reserves terabytes of address space for its shadow
+// memory when the program starts, which no memory limit would allow
+const SANITIZED: Limits = Limits {
+    memory_bytes: u64::MAX,
+    ..sandbox::PROGRAM
+};
+
+/// A C program next to the lesson function it mirrors
+pub struct Program {
+    pub name: &'static str,
+    /// Path of the program relative to the crate root
+    pub file: &'static str,
+    pub source: &'static str,
+    /// The lesson the Rust version is in
+    pub lesson: &'static str,
+    /// The Rust function the program mirrors
+    pub function: &'static str,
+    /// The error example the Rust version is, if it does not compile
+    pub example: Option<&'static str>,
+}
+
+const PROGRAMS: &[Program] = &[
+    Program {
+        name: "change",
+        file: "c/change.c",
+        source: include_str!("../c/change.c"),
+        lesson: "mutable-references",
+        function: "change",
+        example: None,
+    },
+    Program {
+        name: "calculate_length",
+        file: "c/calculate_length.c",
+        source: include_str!("../c/calculate_length.c"),
+        lesson: "borrowing",
+        function: "calculate_length",
+        example: None,
+    },
+    Program {
+        name: "dangle",
+        file: "c/dangle.c",
+        source: include_str!("../c/dangle.c"),
+        lesson: "dangling-references",
+        function: "dangle",
+        example: Some("dangle"),
+    },
+];
+
+/// What happened when a program was compiled and run
+pub struct Outcome {
+    /// Whether it was compiled with AddressSanitizer
+    pub sanitized: bool,
+    /// The compiler's run, whose stderr holds any warnings
+    pub compile: Run,
+    /// The program's run, if it compiled
+    pub run: Option<Run>,
+}
+
+pub fn all() -> &'static [Program] {
+    PROGRAMS
+}
+
+pub fn find(name: &str) -> Option<&'static Program> {
+    let name = name.trim().trim_end_matches(".c").replace('-', "_");
+
+    PROGRAMS.iter().find(|program| program.name == name)
+}
+
+/// The C compiler to use, honouring `CC` the same way `make` does
+pub fn compiler() -> String {
+    env::var("CC").unwrap_or_else(|_| String::from("cc"))
+}
+
+/// Whether the C compiler can build a program with AddressSanitizer that
+/// then runs, which needs the sanitizer's runtime library to be installed
+pub fn sanitizer_available() -> io::Result<bool> {
+    let dir = TempDir::new("references-c")?;
+    fs::write(dir.path().join("probe.c"), "int main(void) { return 0; }\n")?;
+
+    let compile = Sandbox::compiler().run(
+        compiler(),
+        [SANITIZER_FLAG, "-o", "probe", "probe.c"],
+        dir.path(),
+    )?;
+    if !compile.success() {
+        return Ok(false);
+    }
+
+    let run = Sandbox::new(SANITIZED, &[]).run(dir.path().join("probe"), NO_ARGS, dir.path())?;
+    Ok(run.success())
+}
+
+impl Program {
+    /// The compiler command the program is built with, for showing
+    pub fn command(&self, sanitized: bool) -> String {
+        let mut command = vec![compiler()];
+        command.extend(FLAGS.iter().map(|flag| flag.to_string()));
+        if sanitized {
+            command.push(SANITIZER_FLAG.to_string());
+        }
+
+        command.join(" ")
+    }
+
+    /// Compiles the program, with AddressSanitizer if `sanitized`, and
+    /// runs it if it compiled
+    pub fn run(&self, sanitized: bool) -> io::Result<Outcome> {
+        let dir = TempDir::new("references-c")?;
+        let file = format!("{}.c", self.name);
+        fs::write(dir.path().join(&file), self.source)?;
+
+        let mut args: Vec<&str> = FLAGS.to_vec();
+        if sanitized {
+            args.push(SANITIZER_FLAG);
+        }
+        args.extend(["-o", self.name, &file]);
+        let compile = Sandbox::compiler().run(compiler(), args, dir.path())?;
+
+        let run = if compile.success() {
+            let limits = if sanitized {
+                SANITIZED
+            } else {
+                sandbox::PROGRAM
+            };
+            let program = dir.path().join(self.name);
+            let mut run = Sandbox::new(limits, &[]).run(program, NO_ARGS, dir.path())?;
+            // The sanitizer names files by the scratch directory they were in
+            let scratch = format!("{}/", dir.path().display());
+            run.stderr = run.stderr.replace(&scratch, "");
+            Some(run)
+        } else {
+            None
+        };
+
+        Ok(Outcome {
+            sanitized,
+            compile,
+            run,
+        })
+    }
+}
+
+/// A sanitizer report without the table of shadow bytes that follows its
+/// summary, which says nothing to someone learning about dangling pointers
+pub fn report(stderr: &str) -> String {
+    let mut lines = Vec::new();
+
+    for line in stderr.lines() {
+        lines.push(line);
+        if line.starts_with("SUMMARY: ") {
+            break;
+        }
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,324 @@
+// Project: references
+// Author: Greg Folker
+
+// What `rustc` said about a snippet, remembered so that the same snippet
+// is not compiled twice. Entries are addressed by a hash of everything
+// that can change the verdict: the source, the name it is compiled under,
+// the edition and flags, and the full `rustc -Vv` output, so that a new
+// compiler never gets an old compiler's verdict
+//
+// Each entry is a small file named after its hash. Reading an entry marks
+// it as used, and once there are too many the least recently used are
+// removed. Snippets that are not remembered yet can be compiled in
+// parallel
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+
+use crate::compile::{self, Outcome, EDITION, FLAGS};
+use crate::progress;
+use crate::sandbox::Exit;
+use crate::tempdir::TempDir;
+
+/// Overrides where verdicts are kept
+pub const DIR_VARIABLE: &str = "REFERENCES_CACHE_DIR";
+
+/// Set to compile every snippet afresh
+pub const DISABLE_VARIABLE: &str = "REFERENCES_NO_CACHE";
+
+/// How many verdicts are kept before the least recently used are removed
+pub const CAPACITY: usize = 2000;
+
+// The first line of every entry, which changes whenever the format does
+const HEADER: &str = "references verdict 1";
+
+pub struct Cache {
+    // Where entries are kept, or none if nothing is remembered
+    dir: Option<PathBuf>,
+    capacity: usize,
+}
+
+impl Cache {
+    /// The cache in `REFERENCES_CACHE_DIR`, or else in `verdicts` in the
+    /// user's data directory. It is disabled by `REFERENCES_NO_CACHE`
+    pub fn open() -> Cache {
+        if env::var_os(DISABLE_VARIABLE).is_some() {
+            return Cache::disabled();
+        }
+
+        let dir = match env::var_os(DIR_VARIABLE).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => progress::data_dir().map(|dir| dir.join("verdicts")),
+        };
+
+        Cache {
+            dir,
+            capacity: CAPACITY,
+        }
+    }
+
+    /// A cache keeping its entries in `dir`, up to `capacity` of them
+    pub fn at(dir: &Path, capacity: usize) -> Cache {
+        Cache {
+            dir: Some(dir.to_path_buf()),
+            capacity,
+        }
+    }
+
+    /// A cache that remembers nothing
+    pub fn disabled() -> Cache {
+        Cache {
+            dir: None,
+            capacity: 0,
+        }
+    }
+
+    /// Compiles `source` as a binary crate named `name`, or returns what
+    /// `rustc` said about it the last time
+    pub fn compile(&self, name: &str, source: &str) -> io::Result<Outcome> {
+        self.compile_all(&[(name, source)])
+            .map(|mut outcomes| outcomes.remove(0))
+    }
+
+    /// Compiles every `(name, source)` snippet, returning the outcomes in
+    /// the same order. Those that are not remembered are compiled in
+    /// parallel, one at a time on each available CPU
+    pub fn compile_all(&self, snippets: &[(&str, &str)]) -> io::Result<Vec<Outcome>> {
+        self.compile_edition(EDITION, snippets)
+    }
+
+    /// Compiles every snippet the same way as `compile_all`, but as the
+    /// given edition rather than the lessons' own
+    pub fn compile_edition(
+        &self,
+        edition: &str,
+        snippets: &[(&str, &str)],
+    ) -> io::Result<Vec<Outcome>> {
+        let version = compile::version_verbose()?;
+        let keys: Vec<String> = snippets
+            .iter()
+            .map(|(name, source)| key(&version, edition, name, source))
+            .collect();
+
+        let mut outcomes: Vec<Option<Outcome>> = Vec::new();
+        for key in &keys {
+            outcomes.push(self.load(key)?);
+        }
+
+        let misses: Vec<usize> = (0..snippets.len())
+            .filter(|&i| outcomes[i].is_none())
+            .collect();
+        let compiled = compile_in_parallel(edition, snippets, &misses)?;
+
+        if !misses.is_empty() {
+            for (&i, outcome) in misses.iter().zip(compiled) {
+                self.store(&keys[i], &outcome)?;
+                outcomes[i] = Some(outcome);
+            }
+            self.evict()?;
+        }
+
+        Ok(outcomes.into_iter().flatten().collect())
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(key))
+    }
+
+    fn load(&self, key: &str) -> io::Result<Option<Outcome>> {
+        let path = match self.path(key) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let outcome = parse(&text);
+        if outcome.is_some() {
+            // Mark the entry as used, so that it is evicted last. A cache
+            // that can only be read is still worth reading
+            if let Ok(file) = File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        }
+        Ok(outcome)
+    }
+
+    fn store(&self, key: &str, outcome: &Outcome) -> io::Result<()> {
+        // A `rustc` the sandbox killed has given no verdict, and may not
+        // be killed next time
+        let status = match outcome.exit {
+            Exit::Code(status) => status,
+            _ => return Ok(()),
+        };
+        let (dir, path) = match (&self.dir, self.path(key)) {
+            (Some(dir), Some(path)) => (dir, path),
+            _ => return Ok(()),
+        };
+
+        fs::create_dir_all(dir)?;
+        let text = format!(
+            "{}\nstatus {}\ncodes {}\n\n{}",
+            HEADER,
+            status,
+            outcome.codes.join(" "),
+            outcome.stderr
+        );
+
+        // Written under another name first, so that another process never
+        // reads half an entry
+        let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temporary, text)?;
+        fs::rename(&temporary, &path)
+    }
+
+    /// Removes the least recently used entries until no more than the
+    /// cache's capacity are left, returning how many were removed
+    pub fn evict(&self) -> io::Result<usize> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => return Ok(0),
+        };
+
+        // Nothing has been stored yet if every compile was killed
+        let listing = match fs::read_dir(dir) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for entry in listing {
+            let entry = entry?;
+            // Only finished entries, not another process's half-written ones
+            if !is_key(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let used = entry.metadata()?.modified()?;
+            entries.push((used, entry.path()));
+        }
+        if entries.len() <= self.capacity {
+            return Ok(0);
+        }
+
+        entries.sort();
+        let excess = entries.len() - self.capacity;
+        for (_, path) in &entries[..excess] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
+        Ok(excess)
+    }
+}
+
+/// The 64-bit FNV-1a hash, which stays the same from one Rust release to
+/// the next unlike the standard library's hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// The address of a snippet's entry. Two different hashes of the same
+// text are combined, since one 64-bit hash over every snippet a class
+// ever compiles is closer to colliding than it needs to be
+fn key(version: &str, edition: &str, name: &str, source: &str) -> String {
+    let text = format!(
+        "{}\0{}\0{}\0{}\0{}\0{}",
+        HEADER,
+        version,
+        edition,
+        FLAGS.join(" "),
+        name,
+        source
+    );
+    let reversed: Vec<u8> = text.bytes().rev().collect();
+
+    format!("{:016x}{:016x}", fnv1a(text.as_bytes()), fnv1a(&reversed))
+}
+
+// Whether a file name is an entry's, which is 32 hex digits
+fn is_key(name: &str) -> bool {
+    name.len() == 32 && name.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn parse(text: &str) -> Option<Outcome> {
+    let mut lines = text.splitn(4, '\n');
+    if lines.next()? != HEADER {
+        return None;
+    }
+
+    let status: i32 = lines.next()?.strip_prefix("status ")?.parse().ok()?;
+    let codes = lines
+        .next()?
+        .strip_prefix("codes")?
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let stderr = lines.next()?.strip_prefix('\n')?.to_string();
+
+    Some(Outcome {
+        success: status == 0,
+        exit: Exit::Code(status),
+        codes,
+        stderr,
+        cached: true,
+    })
+}
+
+// Compiles the snippets at `indices`, each in a directory of its own,
+// with as many at once as there are CPUs
+fn compile_in_parallel(
+    edition: &str,
+    snippets: &[(&str, &str)],
+    indices: &[usize],
+) -> io::Result<Vec<Outcome>> {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(indices.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<io::Result<Outcome>>>> =
+        Mutex::new(indices.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let n = next.fetch_add(1, Ordering::SeqCst);
+                let (name, source) = match indices.get(n) {
+                    Some(&i) => snippets[i],
+                    None => break,
+                };
+
+                let outcome = TempDir::new("references-compile").and_then(|dir| {
+                    let mut outcome =
+                        compile::compile_edition_in(dir.path(), edition, name, source)?;
+                    // Paths in the scratch directory would differ next time
+                    let scratch = format!("{}/", dir.path().display());
+                    outcome.stderr = outcome.stderr.replace(&scratch, "");
+                    Ok(outcome)
+                });
+                if let Ok(mut results) = results.lock() {
+                    results[n] = Some(outcome);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .map_err(|_| io::Error::other("a compile thread panicked"))?
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(io::Error::other("snippet was not compiled"))))
+        .collect()
+}
@@ -0,0 +1,219 @@
+// Project: references
+// Author: Greg Folker
+
+// The borrowing errors `rustc` reports, each explained for a beginner and
+// tied to the lesson that demonstrates it. Along with `rustc --explain`,
+// which ships with the compiler, this works without a network connection
+
+use std::io;
+
+use crate::compile;
+use crate::examples;
+use crate::lesson;
+
+/// What an error code means and where the lessons show it
+pub struct Entry {
+    pub code: &'static str,
+    /// What the error means, in plain words
+    pub summary: &'static str,
+    /// Id of the lesson that teaches the rule behind the error
+    pub lesson: &'static str,
+    /// The `@compile_fail` example that fails with this error, if any
+    pub example: Option<&'static str>,
+    /// The bundled toy program that fails with this error, if any
+    pub toy: Option<&'static str>,
+}
+
+static ENTRIES: &[Entry] = &[
+    Entry {
+        code: "E0106",
+        summary: "\
+A function returns a reference, but nothing says what it borrows from.
+A reference can only be returned if it points into one of the
+arguments. A value created inside the function is dropped when it
+returns, so return the value itself and let ownership move out.",
+        lesson: "dangling-references",
+        example: Some("dangle"),
+        toy: Some("dangle"),
+    },
+    Entry {
+        code: "E0382",
+        summary: "\
+A value was used after it had been moved somewhere else. Passing a
+`String` by value hands ownership over, so the old name can no longer
+be used. Pass a reference such as `&s` instead to only lend it.",
+        lesson: "borrowing",
+        example: None,
+        toy: None,
+    },
+    Entry {
+        code: "E0499",
+        summary: "\
+Two `&mut` references to the same value were live at the same time.
+Only one mutable reference may exist while it is still in use. Finish
+using the first one, or put it in its own scope, before taking another.",
+        lesson: "mutable-aliasing",
+        example: Some("double-mutable-borrow"),
+        toy: Some("double-mutable-borrow"),
+    },
+    Entry {
+        code: "E0502",
+        summary: "\
+A value was borrowed mutably while a shared `&` reference to it was
+still in use, or the other way round. Any number of `&` references may
+coexist, but not alongside a `&mut`. Use the shared references for the
+last time before taking the mutable one.",
+        lesson: "shared-and-mutable",
+        example: Some("shared-then-mutable"),
+        toy: Some("shared-then-mutable"),
+    },
+    Entry {
+        code: "E0505",
+        summary: "\
+A value was moved while a reference to it was still in use. The
+reference would be left pointing at something that has gone. Use the
+reference for the last time before the move, or lend the value instead
+of moving it.",
+        lesson: "borrowing",
+        example: None,
+        toy: Some("move-while-borrowed"),
+    },
+    Entry {
+        code: "E0506",
+        summary: "\
+A value was assigned to while a reference to it was still in use.
+Changing it would change what the reference sees behind its back.",
+        lesson: "shared-and-mutable",
+        example: None,
+        toy: None,
+    },
+    Entry {
+        code: "E0515",
+        summary: "\
+A function returns a reference to one of its own local values, which
+is dropped when the function returns. Return the value itself instead.",
+        lesson: "dangling-references",
+        example: None,
+        toy: None,
+    },
+    Entry {
+        code: "E0594",
+        summary: "\
+Something was assigned through a `&` reference. Only a `&mut`
+reference allows changing the value it points at.",
+        lesson: "mutable-references",
+        example: None,
+        toy: None,
+    },
+    Entry {
+        code: "E0596",
+        summary: "\
+A value was borrowed mutably through something that is not mutable,
+such as a `&` reference or a variable declared without `mut`. Both the
+variable and the reference have to be `mut` to change a value.",
+        lesson: "mutable-references",
+        example: Some("immutable-change"),
+        toy: Some("immutable-change"),
+    },
+    Entry {
+        code: "E0597",
+        summary: "\
+A reference outlived the value it points at, which was dropped at the
+end of its scope while still borrowed. Keep the value alive for as long
+as the reference is used.",
+        lesson: "scoped-borrows",
+        example: None,
+        toy: None,
+    },
+    Entry {
+        code: "E0716",
+        summary: "\
+A reference was taken to a temporary value that is dropped at the end
+of the statement. Store the value in a variable first and borrow that.",
+        lesson: "dangling-references",
+        example: None,
+        toy: None,
+    },
+];
+
+/// Every error code the lessons can explain, in code order
+pub fn all() -> &'static [Entry] {
+    ENTRIES
+}
+
+/// The entry for an error code such as `E0499`
+pub fn find(code: &str) -> Option<&'static Entry> {
+    ENTRIES.iter().find(|entry| entry.code == code)
+}
+
+/// The entries whose code, summary or `rustc --explain` text mentions
+/// `query`, ignoring case. A code can be given with or without its `E`
+pub fn search(query: &str) -> io::Result<Vec<&'static Entry>> {
+    let query = query.to_lowercase();
+    let mut found = Vec::new();
+
+    for entry in ENTRIES {
+        let code = entry.code.to_lowercase();
+        let matches = code == query
+            || code[1..] == query
+            || entry.summary.to_lowercase().contains(&query)
+            || entry.explanation()?.to_lowercase().contains(&query);
+
+        if matches {
+            found.push(entry);
+        }
+    }
+
+    Ok(found)
+}
+
+impl Entry {
+    /// The first sentence of the summary
+    pub fn headline(&self) -> String {
+        let summary = self.summary.replace('\n', " ");
+        match summary.find(". ") {
+            Some(end) => summary[..=end].to_string(),
+            None => summary,
+        }
+    }
+
+    /// What `rustc --explain` says about the error
+    pub fn explanation(&self) -> io::Result<String> {
+        compile::explain(self.code)
+    }
+
+    /// Where to see the error in the lessons, one line for each place
+    pub fn pointers(&self) -> String {
+        let mut out = String::new();
+
+        if let Some(lesson) = lesson::find(self.lesson) {
+            out.push_str(&format!(
+                "See the `{}` lesson, \"{}\": `references show {}`\n",
+                lesson.id, lesson.title, lesson.id
+            ));
+        }
+
+        let example = self
+            .example
+            .and_then(|id| examples::find(id).ok().flatten());
+        if let Some(example) = example {
+            let lines: Vec<String> = example.lines.iter().map(|l| l.to_string()).collect();
+            out.push_str(&format!(
+                "The same error is commented out on line{} {} of {}: `references whatif {}`\n",
+                if lines.len() == 1 { "" } else { "s" },
+                lines.join(" and "),
+                example.file,
+                example.id
+            ));
+        }
+
+        if let Some(toy) = self.toy {
+            out.push_str(&format!(
+                "Step through it in the toy language: `references toy --explain {}`\n",
+                toy
+            ));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,176 @@
+// Project: references
+// Author: Greg Folker
+
+// Compiling snippets with the local `rustc`, so claims such as "this is a
+// compiler error" can be checked against the real compiler. What `rustc`
+// says about a snippet is remembered in the verdict cache, so an unchanged
+// snippet is only compiled once
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::cache::Cache;
+use crate::json;
+use crate::sandbox::{Exit, Sandbox};
+
+/// The edition the lessons are written against, matching `Cargo.toml`
+pub const EDITION: &str = "2018";
+
+/// The flags every snippet is compiled with, besides its edition and names
+pub const FLAGS: &[&str] = &["--crate-type", "bin", "--color", "never"];
+
+/// What happened when a snippet was handed to `rustc`
+pub struct Outcome {
+    pub success: bool,
+    /// How `rustc` ended, which is only a verdict if it exited by itself
+    pub exit: Exit,
+    /// Error codes such as `E0499`, in the order `rustc` reported them
+    pub codes: Vec<String>,
+    pub stderr: String,
+    /// Whether this came from the verdict cache rather than from `rustc`
+    pub cached: bool,
+}
+
+/// Compiles `source` as a binary crate named `name`, so that the file
+/// name in any diagnostics reads `name.rs`, unless the verdict cache
+/// already knows what `rustc` makes of it
+pub fn compile(name: &str, source: &str) -> io::Result<Outcome> {
+    Cache::open().compile(name, source)
+}
+
+/// Compiles `source` in `dir`, leaving the program at `dir/name` if it
+/// compiled, so that it can be run. `rustc` runs in the compiler sandbox,
+/// since the source may be a student's
+pub fn compile_in(dir: &Path, name: &str, source: &str) -> io::Result<Outcome> {
+    compile_edition_in(dir, EDITION, name, source)
+}
+
+/// Compiles `source` in `dir` the same way, but as the given edition
+pub fn compile_edition_in(
+    dir: &Path,
+    edition: &str,
+    name: &str,
+    source: &str,
+) -> io::Result<Outcome> {
+    let (exit, stderr) = run_in(dir, edition, &[], name, source)?;
+
+    Ok(Outcome {
+        success: exit == Exit::Code(0),
+        exit,
+        codes: error_codes(&stderr),
+        stderr,
+        cached: false,
+    })
+}
+
+/// Compiles `source` in `dir` the same way as `compile_in`, but with the
+/// diagnostics in `stderr` as `rustc`'s JSON, one per line, for reading
+/// with `diagnose::parse`. Each one carries the usual text in `rendered`
+pub fn compile_json_in(dir: &Path, name: &str, source: &str) -> io::Result<Outcome> {
+    let (exit, stderr) = run_in(dir, EDITION, &["--error-format", "json"], name, source)?;
+
+    Ok(Outcome {
+        success: exit == Exit::Code(0),
+        exit,
+        codes: json_error_codes(&stderr),
+        stderr,
+        cached: false,
+    })
+}
+
+// Runs `rustc` on `source` in the compiler sandbox, returning how it
+// ended and what it printed, along with how it was stopped if it did not
+// exit by itself
+fn run_in(
+    dir: &Path,
+    edition: &str,
+    extra: &[&str],
+    name: &str,
+    source: &str,
+) -> io::Result<(Exit, String)> {
+    let file = format!("{}.rs", name);
+    fs::write(dir.join(&file), source)?;
+
+    let mut args = vec!["--edition", edition];
+    args.extend(FLAGS);
+    args.extend(extra);
+    args.extend(["-o", name, &file]);
+    let run = Sandbox::compiler().run(rustc(), args, dir)?;
+
+    let mut stderr = run.stderr;
+    if !matches!(run.exit, Exit::Code(_)) {
+        stderr.push_str(&format!("\n`rustc` {}\n", run.exit));
+    }
+
+    Ok((run.exit, stderr))
+}
+
+/// The compiler to use, honouring `RUSTC` the same way Cargo does
+pub fn rustc() -> String {
+    env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"))
+}
+
+/// The first line of `rustc --version`, such as `rustc 1.70.0 (90c541806 2023-05-31)`
+pub fn version() -> io::Result<String> {
+    let output = Command::new(rustc()).arg("--version").output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The whole of `rustc -Vv`, including the commit and LLVM version, which
+/// is asked for once and then remembered
+pub fn version_verbose() -> io::Result<String> {
+    static VERBOSE: OnceLock<String> = OnceLock::new();
+    if let Some(verbose) = VERBOSE.get() {
+        return Ok(verbose.clone());
+    }
+
+    let output = Command::new(rustc()).arg("-Vv").output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`{} -Vv` failed: {}",
+            rustc(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let verbose = String::from_utf8_lossy(&output.stdout).into_owned();
+    Ok(VERBOSE.get_or_init(|| verbose).clone())
+}
+
+/// The long explanation of an error code, from `rustc --explain`
+pub fn explain(code: &str) -> io::Result<String> {
+    let output = Command::new(rustc()).args(["--explain", code]).output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`rustc --explain {}` failed: {}",
+            code,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn error_codes(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("error["))
+        .filter_map(|rest| rest.split(']').next())
+        .map(String::from)
+        .collect()
+}
+
+fn json_error_codes(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .filter_map(|line| json::parse(line).ok())
+        .filter(|value| value.get("level").as_str() == Some("error"))
+        .filter_map(|value| value.get("code").get("code").as_str().map(String::from))
+        .collect()
+}
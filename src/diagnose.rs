@@ -0,0 +1,257 @@
+// Project: references
+// Author: Greg Folker
+
+// Compiles a student's own file with `rustc --error-format=json` and
+// explains each error by pointing at the lesson that demonstrates it
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::catalog;
+use crate::compile::{self, EDITION};
+use crate::json::{self, Value};
+use crate::sandbox::{Exit, Sandbox};
+use crate::tempdir::TempDir;
+
+/// An error or warning as `rustc` reports it
+pub struct Diagnostic {
+    /// `error` or `warning`
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub spans: Vec<Span>,
+    /// `help:` and `note:` lines that go along with it
+    pub notes: Vec<String>,
+}
+
+/// A place in the student's code a diagnostic points at
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// The source line and the columns to underline, from 1
+    pub text: String,
+    pub highlight: (usize, usize),
+    pub label: Option<String>,
+    pub primary: bool,
+}
+
+/// Compiles the file at `path` and returns the errors in it
+pub fn check(path: &Path) -> io::Result<Vec<Diagnostic>> {
+    let dir = TempDir::new("references-diagnose")?;
+    let source = fs::canonicalize(path)?;
+
+    // Only type and borrow checking is needed, and compiling as a library
+    // means a file without `main` is fine
+    let run = Sandbox::compiler().run(
+        compile::rustc(),
+        [
+            OsStr::new("--edition"),
+            OsStr::new(EDITION),
+            OsStr::new("--crate-type"),
+            OsStr::new("lib"),
+            OsStr::new("--emit"),
+            OsStr::new("metadata"),
+            OsStr::new("--error-format"),
+            OsStr::new("json"),
+            OsStr::new("--color"),
+            OsStr::new("never"),
+            OsStr::new("--out-dir"),
+            dir.path().as_os_str(),
+            source.as_os_str(),
+        ],
+        dir.path(),
+    )?;
+    if !matches!(run.exit, Exit::Code(_)) {
+        return Err(io::Error::other(format!("`rustc` {}", run.exit)));
+    }
+
+    let mut diagnostics = parse(&run.stderr).map_err(io::Error::other)?;
+    diagnostics.retain(|d| d.level == "error");
+
+    // Show the file the way the student named it
+    let display = path.display().to_string();
+    let absolute = source.display().to_string();
+    for span in diagnostics.iter_mut().flat_map(|d| &mut d.spans) {
+        if span.file == absolute {
+            span.file = display.clone();
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// Reads the diagnostics in `rustc`'s JSON output, one per line, leaving
+/// out summaries such as "aborting due to 2 previous errors" that are
+/// not about any place in the code
+pub fn parse(output: &str) -> Result<Vec<Diagnostic>, json::Error> {
+    let mut diagnostics = Vec::new();
+
+    for line in output.lines().filter(|line| line.starts_with('{')) {
+        let value = json::parse(line)?;
+        let spans: Vec<Span> = value.get("spans").as_array().iter().map(span).collect();
+
+        if spans.is_empty() {
+            continue;
+        }
+
+        diagnostics.push(Diagnostic {
+            level: value.get("level").as_str().unwrap_or("error").to_string(),
+            code: value
+                .get("code")
+                .get("code")
+                .as_str()
+                .map(|code| code.to_string()),
+            message: value.get("message").as_str().unwrap_or("").to_string(),
+            spans,
+            notes: value
+                .get("children")
+                .as_array()
+                .iter()
+                .filter_map(|child| {
+                    let level = child.get("level").as_str()?;
+                    let message = child.get("message").as_str()?;
+                    Some(format!("{}: {}", level, message))
+                })
+                .collect(),
+        });
+    }
+
+    Ok(diagnostics)
+}
+
+/// What `rustc` would have printed without `--error-format=json`: the
+/// `rendered` text of each diagnostic, with any other lines kept as
+/// they are
+pub fn rendered(output: &str) -> String {
+    let mut text = String::new();
+
+    for line in output.lines() {
+        match json::parse(line) {
+            Ok(value) if line.starts_with('{') => {
+                text.push_str(value.get("rendered").as_str().unwrap_or(""));
+            }
+            _ => {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+
+    text
+}
+
+fn span(value: &Value) -> Span {
+    let number = |key: &str| value.get(key).as_usize().unwrap_or(0);
+    let text = &value.get("text").as_array().first().unwrap_or(&Value::Null);
+
+    Span {
+        file: value.get("file_name").as_str().unwrap_or("").to_string(),
+        line: number("line_start"),
+        column: number("column_start"),
+        text: text.get("text").as_str().unwrap_or("").to_string(),
+        highlight: (
+            text.get("highlight_start").as_usize().unwrap_or(1),
+            text.get("highlight_end").as_usize().unwrap_or(1),
+        ),
+        label: value.get("label").as_str().map(|label| label.to_string()),
+        primary: value.get("is_primary").as_bool().unwrap_or(false),
+    }
+}
+
+/// A diagnostic with the student's code quoted, followed by what it
+/// means and which lesson shows it
+pub fn explain(diagnostic: &Diagnostic) -> String {
+    let mut out = match &diagnostic.code {
+        Some(code) => format!("{}[{}]: {}\n", diagnostic.level, code, diagnostic.message),
+        None => format!("{}: {}\n", diagnostic.level, diagnostic.message),
+    };
+    out.push_str(&quote(&diagnostic.spans, &diagnostic.notes));
+
+    let entry = match diagnostic.code.as_deref().and_then(catalog::find) {
+        Some(entry) => entry,
+        None => {
+            out.push_str("\nNone of the lessons cover this error.\n");
+            return out;
+        }
+    };
+
+    out.push('\n');
+    out.push_str(entry.summary);
+    out.push('\n');
+
+    out.push('\n');
+    out.push_str(&entry.pointers());
+
+    out
+}
+
+// The lines a diagnostic points at, underlined the way `rustc` does, and
+// its notes
+fn quote(spans: &[Span], notes: &[String]) -> String {
+    let mut spans: Vec<&Span> = spans.iter().collect();
+    spans.sort_by_key(|span| (span.line, span.column));
+
+    let primary = spans.iter().find(|span| span.primary).or(spans.first());
+    let width = spans
+        .iter()
+        .map(|span| span.line.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    let mut out = String::new();
+    if let Some(primary) = primary {
+        out.push_str(&format!(
+            "{:width$}--> {}:{}:{}\n",
+            "",
+            primary.file,
+            primary.line,
+            primary.column,
+            width = width
+        ));
+    }
+    out.push_str(&format!("{:width$} |\n", "", width = width));
+
+    let mut previous = None;
+    for span in spans {
+        if previous.is_some_and(|line| span.line > line + 1) {
+            out.push_str("...\n");
+        }
+        if previous != Some(span.line) {
+            out.push_str(&format!(
+                "{:>width$} | {}\n",
+                span.line,
+                span.text,
+                width = width
+            ));
+        }
+        previous = Some(span.line);
+
+        let (start, end) = span.highlight;
+        let marker = if span.primary { "^" } else { "-" };
+        let underline = format!(
+            "{}{}",
+            " ".repeat(start.saturating_sub(1)),
+            marker.repeat(end.saturating_sub(start).max(1))
+        );
+        out.push_str(
+            format!(
+                "{:width$} | {} {}",
+                "",
+                underline,
+                span.label.as_deref().unwrap_or(""),
+                width = width
+            )
+            .trim_end(),
+        );
+        out.push('\n');
+    }
+    out.push_str(&format!("{:width$} |\n", "", width = width));
+    for note in notes {
+        out.push_str(&format!("{:width$} = {}\n", "", note, width = width));
+    }
+
+    out
+}
@@ -0,0 +1,109 @@
+// Project: references
+// Author: Greg Folker
+
+// The same snippet compiled as each edition of Rust, to show where older
+// code and blog posts disagree with what `rustc` says today. Verdicts come
+// from the local `rustc` and nowhere else
+//
+// Most of the borrowing in the lessons behaves the same in every edition
+// on a current compiler. Non-lexical lifetimes came with the 2018 edition
+// in Rust 1.31 and to the 2015 edition in Rust 1.36, so the `r1` and `r2`
+// then `r3` example compiles as 2015 too on any compiler since. What an
+// older compiler said about it cannot be shown with the one installed here
+
+use std::io;
+
+use crate::cache::Cache;
+use crate::compile::Outcome;
+use crate::examples;
+use crate::lesson::{self, Lesson};
+use crate::source::Error;
+
+/// The editions snippets are compared across, oldest first
+pub const EDITIONS: [&str; 3] = ["2015", "2018", "2021"];
+
+/// A snippet to compile as each edition
+pub struct Snippet {
+    /// A lesson or example id, which is also the name it is compiled under
+    pub name: String,
+    pub source: String,
+}
+
+/// What `rustc` made of one snippet as each edition
+pub struct Comparison {
+    pub name: String,
+    /// One outcome for each of `EDITIONS`, in the same order
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Comparison {
+    /// Whether every edition gave the same verdict and error codes
+    pub fn verdicts_agree(&self) -> bool {
+        self.outcomes
+            .windows(2)
+            .all(|pair| (pair[0].success, &pair[0].codes) == (pair[1].success, &pair[1].codes))
+    }
+
+    /// Whether every edition printed exactly the same diagnostics
+    pub fn diagnostics_agree(&self) -> bool {
+        self.outcomes
+            .windows(2)
+            .all(|pair| pair[0].stderr == pair[1].stderr)
+    }
+}
+
+/// The lesson's own program, followed by each of its commented-out
+/// errors as a program of its own
+pub fn lesson_snippets(lesson: &Lesson) -> Result<Vec<Snippet>, Error> {
+    let mut snippets = vec![Snippet {
+        name: lesson.id.to_string(),
+        source: lesson.program()?,
+    }];
+
+    for example in examples::all()? {
+        if lesson::for_example(&example).map(|l| l.id) == Some(lesson.id) {
+            snippets.push(Snippet {
+                name: example.id,
+                source: example.source,
+            });
+        }
+    }
+
+    Ok(snippets)
+}
+
+/// Compiles every snippet as each of `EDITIONS`, the snippets of one
+/// edition in parallel
+pub fn compare(snippets: &[Snippet], cache: &Cache) -> io::Result<Vec<Comparison>> {
+    let pairs: Vec<(&str, &str)> = snippets
+        .iter()
+        .map(|snippet| (snippet.name.as_str(), snippet.source.as_str()))
+        .collect();
+
+    let mut comparisons: Vec<Comparison> = snippets
+        .iter()
+        .map(|snippet| Comparison {
+            name: snippet.name.clone(),
+            outcomes: Vec::new(),
+        })
+        .collect();
+    for edition in EDITIONS {
+        let outcomes = cache.compile_edition(edition, &pairs)?;
+        for (comparison, outcome) in comparisons.iter_mut().zip(outcomes) {
+            comparison.outcomes.push(outcome);
+        }
+    }
+
+    Ok(comparisons)
+}
+
+/// A verdict short enough for a table: `compiles`, or the error codes
+pub fn verdict(outcome: &Outcome) -> String {
+    if outcome.success {
+        String::from("compiles")
+    } else if outcome.codes.is_empty() {
+        String::from("fails")
+    } else {
+        outcome.codes.join(", ")
+    }
+}
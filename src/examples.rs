@@ -0,0 +1,437 @@
+// Project: references
+// Author: Greg Folker
+
+// The commented-out code in the lesson files that is claimed to be a
+// compiler error, turned back into programs that can be handed to `rustc`
+//
+// Each piece of commented-out code is introduced by a marker naming the
+// example and the error it is expected to fail with:
+//
+//     // @compile_fail double-mutable-borrow E0499
+//     // let r2 = &mut s;
+//
+// Several pieces of code can share one example, such as the definition of
+// `dangle` and the commented-out call to it, and are uncommented together
+
+use crate::source::{self, Error};
+
+/// The comment that introduces a commented-out example
+pub const MARKER: &str = "@compile_fail";
+
+/// A commented-out snippet that is expected to fail to compile
+pub struct Example {
+    pub id: String,
+    /// The `rustc` error code the snippet is expected to fail with
+    pub code: String,
+    /// Path of the lesson file the example was found in
+    pub file: &'static str,
+    /// Line of the first line of code in each commented-out block
+    pub lines: Vec<usize>,
+    /// The comments leading up to and inside the commented-out code
+    pub commentary: String,
+    /// The commented-out code itself, uncommented
+    pub code_lines: String,
+    /// Functions the commented-out code sits in or defines, which is how
+    /// an example is tied back to the lesson that teaches it
+    pub functions: Vec<String>,
+    /// A complete program with the example uncommented
+    pub source: String,
+}
+
+impl Example {
+    /// The reconstructed program without the header saying where it came
+    /// from or the marker that would give the error away
+    pub fn program(&self) -> String {
+        let marked = self.marked_program();
+        let lines: Vec<&str> = marked
+            .lines()
+            .filter(|line| !line.contains(MARKER))
+            .collect();
+
+        lines.join("\n")
+    }
+
+    /// The reconstructed program without its header, but still with the
+    /// markers that anchor the code after them
+    pub fn marked_program(&self) -> String {
+        let lines: Vec<&str> = self
+            .source
+            .lines()
+            .skip_while(|line| line.starts_with("// "))
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+/// Every example in every lesson file
+pub fn all() -> Result<Vec<Example>, Error> {
+    let mut examples = Vec::new();
+
+    for (file, text) in source::SOURCES {
+        examples.extend(extract(file, &source::resolved(file, text)?)?);
+    }
+
+    Ok(examples)
+}
+
+pub fn find(id: &str) -> Result<Option<Example>, Error> {
+    Ok(all()?.into_iter().find(|example| example.id == id))
+}
+
+/// The example named `id` with its `{@anchor}` references and `@anchor`
+/// comments left in, to be resolved against wherever its code ends up
+pub fn find_unresolved(id: &str) -> Result<Option<Example>, Error> {
+    for (file, text) in source::SOURCES {
+        let found = extract(file, text)?
+            .into_iter()
+            .find(|example| example.id == id);
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+
+    Ok(None)
+}
+
+// One commented-out block of code following a marker
+struct Block {
+    id: String,
+    code: String,
+    marker: usize,
+    // First and last line of the commented-out code, inclusive
+    start: usize,
+    end: usize,
+}
+
+// A function defined at the top level of a lesson file
+struct Function {
+    name: String,
+    start: usize,
+    end: usize,
+}
+
+pub fn extract(file: &'static str, text: &str) -> Result<Vec<Example>, Error> {
+    let lines: Vec<&str> = text.lines().collect();
+    let functions = functions(&lines);
+    let blocks = blocks(file, &lines)?;
+
+    let mut ids: Vec<&str> = Vec::new();
+    for block in &blocks {
+        if !ids.contains(&block.id.as_str()) {
+            ids.push(&block.id);
+        }
+    }
+
+    ids.into_iter()
+        .map(|id| {
+            let blocks: Vec<&Block> = blocks.iter().filter(|b| b.id == id).collect();
+            example(file, &lines, &functions, &blocks)
+        })
+        .collect()
+}
+
+fn blocks(file: &'static str, lines: &[&str]) -> Result<Vec<Block>, Error> {
+    let mut blocks = Vec::new();
+
+    for (marker, line) in lines.iter().enumerate() {
+        let rest = match line.trim_start().strip_prefix("//") {
+            Some(rest) => rest.trim(),
+            None => continue,
+        };
+        let rest = match rest.strip_prefix(MARKER) {
+            Some(rest) => rest,
+            None => continue,
+        };
+
+        let error = |message: &str| Error {
+            file,
+            line: marker + 1,
+            message: message.to_string(),
+        };
+
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        let (id, code) = match words[..] {
+            [id, code] if is_error_code(code) => (id, code),
+            _ => return Err(error("expected `// @compile_fail <example> <error code>`")),
+        };
+
+        let start = marker + 1;
+        let mut end = start;
+        while end < lines.len() && is_comment(lines[end]) {
+            end += 1;
+        }
+        if end == start {
+            return Err(error("no commented-out code follows this marker"));
+        }
+
+        blocks.push(Block {
+            id: id.to_string(),
+            code: code.to_string(),
+            marker,
+            start,
+            end: end - 1,
+        });
+    }
+
+    Ok(blocks)
+}
+
+fn example(
+    file: &'static str,
+    lines: &[&str],
+    functions: &[Function],
+    blocks: &[&Block],
+) -> Result<Example, Error> {
+    let first = blocks[0];
+    let error = |block: &Block, message: String| Error {
+        file,
+        line: block.marker + 1,
+        message,
+    };
+
+    if let Some(other) = blocks.iter().find(|b| b.code != first.code) {
+        return Err(error(
+            other,
+            format!(
+                "'{}' is expected to fail with {} above",
+                first.id, first.code
+            ),
+        ));
+    }
+
+    let mut uncommented: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    for block in blocks {
+        for line in &mut uncommented[block.start..=block.end] {
+            *line = uncomment(line);
+        }
+    }
+
+    // Code inside a function is run from `main`, code at the top level is
+    // kept as an item next to it
+    let enclosing = |block: &Block| {
+        functions
+            .iter()
+            .position(|f| f.start < block.start && block.end < f.end)
+    };
+
+    let mut body = None;
+    let mut items: Vec<(usize, usize)> = Vec::new();
+    for block in blocks {
+        match enclosing(block) {
+            Some(f) if body.is_some() && body != Some(f) => {
+                return Err(error(
+                    block,
+                    format!("'{}' is spread over more than one function", first.id),
+                ));
+            }
+            Some(f) => body = Some(f),
+            None => items.push((block.start, block.end)),
+        }
+    }
+
+    let main = match body {
+        Some(f) => {
+            let f = &functions[f];
+            let mut main = vec![String::from("fn main() {")];
+            main.extend(uncommented[f.start + 1..=f.end].iter().cloned());
+            main.join("\n")
+        }
+        None => String::from("fn main() {}"),
+    };
+
+    // Commented-out definitions stand in for the real ones with the same
+    // name, such as the `&String` version of `change`
+    let replaced: Vec<String> = items
+        .iter()
+        .filter_map(|&(start, _)| function_name(&uncommented[start]))
+        .collect();
+
+    let mut texts = vec![main.clone()];
+    texts.extend(
+        items
+            .iter()
+            .map(|&(start, end)| uncommented[start..=end].join("\n")),
+    );
+
+    // Pull in every function the example calls, and every function those
+    // call in turn
+    let mut helpers: Vec<&Function> = Vec::new();
+    loop {
+        let next = functions.iter().find(|f| {
+            Some(f.name.as_str()) != body.map(|b| functions[b].name.as_str())
+                && !replaced.contains(&f.name)
+                && !helpers.iter().any(|h| h.name == f.name)
+                && texts.iter().any(|text| calls(text, &f.name))
+        });
+
+        match next {
+            Some(f) => {
+                helpers.push(f);
+                texts.push(uncommented[f.start..=f.end].join("\n"));
+            }
+            None => break,
+        }
+    }
+    items.extend(helpers.iter().map(|f| (f.start, f.end)));
+    items.sort();
+
+    let mut names: Vec<String> = body.iter().map(|&f| functions[f].name.clone()).collect();
+    names.extend(replaced);
+
+    let code_lines: Vec<String> = blocks
+        .iter()
+        .map(|b| dedent(&uncommented[b.start..=b.end]))
+        .collect();
+
+    let lines_used: Vec<usize> = blocks.iter().map(|b| b.start + 1).collect();
+    let mut source = format!(
+        "// Reconstructed from {} ({} {})\n// example: {}\n// expected: {}\n#![allow(unused)]\n\n{}\n",
+        file,
+        if lines_used.len() == 1 { "line" } else { "lines" },
+        join(&lines_used),
+        first.id,
+        first.code,
+        main
+    );
+    for (start, end) in items {
+        source.push('\n');
+        source.push_str(&uncommented[start..=end].join("\n"));
+        source.push('\n');
+    }
+
+    Ok(Example {
+        id: first.id.clone(),
+        code: first.code.clone(),
+        file,
+        lines: lines_used,
+        commentary: commentary(lines, blocks),
+        code_lines: code_lines.join("\n\n"),
+        functions: names,
+        source,
+    })
+}
+
+fn functions(lines: &[&str]) -> Vec<Function> {
+    let mut functions = Vec::new();
+
+    for (start, line) in lines.iter().enumerate() {
+        let name = match function_name(line) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if let Some(n) = source::closing_line(&lines[start..]) {
+            functions.push(Function {
+                name,
+                start,
+                end: start + n,
+            });
+        }
+    }
+
+    functions
+}
+
+// The name of the function defined by `line` when it starts a definition at
+// the top level of a file
+fn function_name(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("pub ").unwrap_or(line);
+    let rest = rest.strip_prefix("fn ")?;
+    let name: String = rest.chars().take_while(|&c| is_ident(c)).collect();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+fn calls(text: &str, name: &str) -> bool {
+    let call = format!("{}(", name);
+
+    text.match_indices(&call).any(|(i, _)| {
+        !text[..i].ends_with(|c| is_ident(c) || c == '.') && !text[..i].trim_end().ends_with("fn")
+    })
+}
+
+// The comments directly above each block, followed by any comments inside
+// it once it has been uncommented
+fn commentary(lines: &[&str], blocks: &[&Block]) -> String {
+    let mut paragraphs = Vec::new();
+
+    for block in blocks {
+        let mut start = block.marker;
+        while start > 0 && is_comment(lines[start - 1]) {
+            start -= 1;
+        }
+
+        let above = lines[start..block.marker]
+            .iter()
+            .map(|line| line.to_string());
+        let inside = lines[block.start..=block.end]
+            .iter()
+            .map(|line| uncomment(line))
+            .filter(|line| is_comment(line));
+
+        let text: Vec<String> = above
+            .chain(inside)
+            .map(|line| strip_comment(&line).to_string())
+            .collect();
+
+        if !text.is_empty() {
+            paragraphs.push(text.join("\n"));
+        }
+    }
+
+    paragraphs.join("\n\n")
+}
+
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with("//") && !line.contains(MARKER)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let rest = line.trim_start().trim_start_matches('/');
+    rest.strip_prefix(' ').unwrap_or(rest)
+}
+
+// Removes one level of `//` from a line, keeping its indentation
+fn uncomment(line: &str) -> String {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+
+    match rest.strip_prefix("//") {
+        Some(code) => format!("{}{}", indent, code.strip_prefix(' ').unwrap_or(code)),
+        None => line.to_string(),
+    }
+}
+
+// Joins `lines` with the indentation they all share removed
+fn dedent(lines: &[String]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    lines.join("\n")
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_error_code(code: &str) -> bool {
+    code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn join(numbers: &[usize]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    numbers.join(", ")
+}
@@ -0,0 +1,305 @@
+// Project: references
+// Author: Greg Folker
+
+// Exercises made from the commented-out errors. Each one is the example
+// program with its error uncommented, written out for a student to fix.
+// A fix passes once it compiles, prints what the lesson it came from
+// prints, and does not get there by a way around the borrow checker such
+// as cloning the `String`
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::anchors;
+use crate::compile;
+use crate::diagnose::{self, Diagnostic};
+use crate::examples::{self, Example};
+use crate::lesson::{self, Lesson};
+use crate::sandbox::{Sandbox, NO_ARGS};
+use crate::snapshot;
+use crate::source::Error;
+use crate::tempdir::TempDir;
+
+/// The directory exercises are written to unless another is given
+pub const WORKSPACE: &str = "exercises";
+
+// The line in an exercise file that says which exercise it is
+const HEADER: &str = "// exercise: ";
+
+/// Code a fix must not use, and why
+pub struct Rule {
+    pub pattern: &'static str,
+    pub reason: &'static str,
+}
+
+// Rules every exercise is graded against
+static FORBIDDEN: &[Rule] = &[
+    Rule {
+        pattern: ".clone()",
+        reason: "copying the `String` avoids the borrow instead of fixing it",
+    },
+    Rule {
+        pattern: "unsafe",
+        reason: "`unsafe` turns off the checks the exercise is about",
+    },
+];
+
+// Rules for single exercises, along with code that has to stay in the fix
+struct Extra {
+    example: &'static str,
+    forbidden: &'static [Rule],
+    required: &'static [Rule],
+}
+
+static EXTRAS: &[Extra] = &[
+    Extra {
+        example: "dangle",
+        forbidden: &[
+            Rule {
+                pattern: "'static",
+                reason: "a `'static` reference cannot point at a `String` made in the function",
+            },
+            Rule {
+                pattern: "Box::leak",
+                reason: "leaking the `String` keeps it alive forever instead of moving it out",
+            },
+        ],
+        required: &[Rule {
+            pattern: "fn dangle(",
+            reason: "fix `dangle` rather than removing it",
+        }],
+    },
+    Extra {
+        example: "immutable-change",
+        forbidden: &[],
+        required: &[
+            Rule {
+                pattern: "fn change(",
+                reason: "fix `change` rather than removing it",
+            },
+            Rule {
+                pattern: "push_str",
+                reason: "`change` still has to add to the `String` it is given",
+            },
+        ],
+    },
+];
+
+pub struct Exercise {
+    pub example: Example,
+    /// The lesson whose output a fix has to reproduce
+    pub lesson: &'static Lesson,
+    /// The broken program the student starts from
+    pub starter: String,
+    pub expected: String,
+    pub forbidden: Vec<&'static Rule>,
+    pub required: Vec<&'static Rule>,
+}
+
+/// One thing a fix was checked for, and whether it held
+pub struct Check {
+    pub description: String,
+    pub passed: bool,
+    /// Why it failed, such as the compiler's errors
+    pub details: String,
+    /// The compiler's errors as `rustc` reported them, for the check
+    /// that the fix compiles
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Every exercise, one for each commented-out error
+pub fn all() -> Result<Vec<Exercise>, Error> {
+    examples::all()?
+        .into_iter()
+        .map(exercise)
+        .collect::<Result<Vec<Option<Exercise>>, Error>>()
+        .map(|exercises| exercises.into_iter().flatten().collect())
+}
+
+pub fn find(id: &str) -> Result<Option<Exercise>, Error> {
+    Ok(all()?
+        .into_iter()
+        .find(|exercise| exercise.example.id == id))
+}
+
+// Examples outside any lesson, or whose lesson has no recorded output,
+// cannot be graded and are left out
+fn exercise(example: Example) -> Result<Option<Exercise>, Error> {
+    let lesson = match lesson::for_example(&example) {
+        Some(lesson) => lesson,
+        None => return Ok(None),
+    };
+    let expected = match snapshot::expected(lesson) {
+        Some(expected) => expected.to_string(),
+        None => return Ok(None),
+    };
+
+    let extra = EXTRAS.iter().find(|extra| extra.example == example.id);
+    let mut forbidden: Vec<&Rule> = FORBIDDEN.iter().collect();
+    forbidden.extend(extra.iter().flat_map(|extra| extra.forbidden));
+    let required = extra.iter().flat_map(|extra| extra.required).collect();
+
+    let starter = starter(&example, lesson, &expected, &forbidden)?;
+
+    Ok(Some(Exercise {
+        example,
+        lesson,
+        starter,
+        expected,
+        forbidden,
+        required,
+    }))
+}
+
+fn starter(
+    example: &Example,
+    lesson: &Lesson,
+    expected: &str,
+    forbidden: &[&Rule],
+) -> Result<String, Error> {
+    let mut out = format!(
+        "{}{}\n\
+         //\n\
+         // This program does not compile: `rustc` rejects it with {}.\n\
+         // Fix it so that it compiles and prints:\n\
+         //\n",
+        HEADER, example.id, example.code
+    );
+    for line in expected.lines() {
+        out.push_str(&format!("//     {}\n", line));
+    }
+    out.push_str("//\n// without using:\n");
+    for rule in forbidden {
+        out.push_str(&format!("//     {}\n", rule.pattern));
+    }
+    out.push_str(&format!(
+        "//\n// The `{}` lesson explains what is wrong. Check your fix with\n\
+         // `references check <this file>`\n",
+        lesson.id
+    ));
+
+    // Anchors are resolved against the starter itself, so that its
+    // comments point at its own lines rather than at `references.rs`
+    let mut program = match examples::find_unresolved(&example.id)? {
+        Some(unresolved) => unresolved.marked_program(),
+        None => example.program(),
+    };
+
+    // An error outside any function leaves `main` empty, so run the
+    // lesson that uses it instead
+    if program.contains("fn main() {}") {
+        program = program.replace("fn main() {}", &lesson.as_main()?);
+    }
+
+    out.push_str(&program);
+    out.push('\n');
+    anchors::resolve_within(example.file, &out, |line| line.contains(examples::MARKER))
+}
+
+impl Exercise {
+    /// Writes the starting program to `dir`, unless the student already
+    /// has a copy there, and returns where it is
+    pub fn write(&self, dir: &Path) -> io::Result<(PathBuf, bool)> {
+        let path = dir.join(format!("{}.rs", self.example.id));
+        if path.exists() {
+            return Ok((path, false));
+        }
+
+        fs::create_dir_all(dir)?;
+        fs::write(&path, &self.starter)?;
+        Ok((path, true))
+    }
+
+    /// Grades a student's version of the exercise. Compiling and running
+    /// it are only attempted once the code itself passes the rules
+    pub fn grade(&self, code: &str) -> io::Result<Vec<Check>> {
+        let stripped = strip_comments(code);
+        let mut checks = Vec::new();
+
+        for rule in &self.forbidden {
+            checks.push(Check {
+                description: format!("does not use `{}`", rule.pattern),
+                passed: !stripped.contains(rule.pattern),
+                details: rule.reason.to_string(),
+                diagnostics: Vec::new(),
+            });
+        }
+        for rule in &self.required {
+            checks.push(Check {
+                description: format!("still has `{}`", rule.pattern),
+                passed: stripped.contains(rule.pattern),
+                details: rule.reason.to_string(),
+                diagnostics: Vec::new(),
+            });
+        }
+        if checks.iter().any(|check| !check.passed) {
+            return Ok(checks);
+        }
+
+        let dir = TempDir::new("references-check")?;
+        let outcome = compile::compile_json_in(dir.path(), &self.example.id, code)?;
+        let mut diagnostics = diagnose::parse(&outcome.stderr).map_err(io::Error::other)?;
+        diagnostics.retain(|d| d.level == "error");
+        checks.push(Check {
+            description: String::from("compiles"),
+            passed: outcome.success,
+            details: diagnose::rendered(&outcome.stderr),
+            diagnostics,
+        });
+        if !outcome.success {
+            return Ok(checks);
+        }
+
+        // The fix is the student's own code, so it runs in the sandbox in
+        // case it loops forever or eats all the memory
+        let run = Sandbox::program().run(dir.path().join(&self.example.id), NO_ARGS, dir.path())?;
+        let passed = run.success();
+        let mut details = run.stderr;
+        if !passed {
+            details.push_str(&format!("\nThe program {}\n", run.exit));
+        }
+        let stdout = run.stdout;
+        checks.push(Check {
+            description: String::from("runs to the end without panicking"),
+            passed,
+            details,
+            diagnostics: Vec::new(),
+        });
+        checks.push(Check {
+            description: format!("prints what the `{}` lesson prints", self.lesson.id),
+            passed: stdout == self.expected,
+            details: format!(
+                "- expected, + printed\n{}",
+                snapshot::diff(&self.expected, &stdout)
+            ),
+            diagnostics: Vec::new(),
+        });
+
+        Ok(checks)
+    }
+}
+
+/// The exercise an exercise file is for, from its first line or else
+/// from its name
+pub fn id_of(path: &Path, code: &str) -> Option<String> {
+    let header = code
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(HEADER))
+        .map(|id| id.trim().to_string());
+
+    header.or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))
+}
+
+// `code` without `//` comments, so that a rule is not broken by a comment
+// that mentions it
+fn strip_comments(code: &str) -> String {
+    code.lines()
+        .map(|line| match line.find("//") {
+            Some(at) => &line[..at],
+            None => line,
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
@@ -0,0 +1,264 @@
+// Project: references
+// Author: Greg Folker
+
+// Hints for the exercises, from a nudge in the right direction to the
+// working code in `references.rs` that the exercise was broken from. Each
+// level unlocks after another failed check, and what every student has
+// failed and looked at is kept next to their exercise files
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::source::{self, Error};
+
+/// How much a hint gives away
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Where to look
+    Nudge,
+    /// The rule the fix depends on
+    Concept,
+    /// The working code the exercise was made from
+    NearSolution,
+}
+
+pub const LEVELS: [Level; 3] = [Level::Nudge, Level::Concept, Level::NearSolution];
+
+/// Failed checks after which each level unlocks. The first failure is
+/// often only a typo, so the nudge waits for a second one
+pub const UNLOCK_AFTER: [usize; 3] = [2, 3, 5];
+
+/// The file in an exercise directory that records hint usage
+pub const RECORD_FILE: &str = ".hints";
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Nudge => "nudge",
+            Level::Concept => "concept",
+            Level::NearSolution => "near solution",
+        }
+    }
+
+    /// How many failed checks it takes to unlock this level
+    pub fn failures_needed(self) -> usize {
+        UNLOCK_AFTER[self as usize]
+    }
+}
+
+pub struct Hint {
+    pub level: Level,
+    pub text: String,
+}
+
+// The hints for one exercise. The near-solution hint is followed by the
+// function from `references.rs` that does the same thing correctly
+struct Hints {
+    example: &'static str,
+    nudge: &'static str,
+    concept: &'static str,
+    solution: &'static str,
+    working: &'static str,
+}
+
+static HINTS: &[Hints] = &[
+    Hints {
+        example: "double-mutable-borrow",
+        nudge: "\
+Look at where `r1` is used for the last time, and where `r2` is taken.",
+        concept: "\
+Only one `&mut` reference to `s` may be in use at a time. A reference
+stops being in use after its last use, or at the end of the block it was
+declared in, so the second one has to come after the first is done.",
+        solution: "\
+`scoped_borrows` takes two mutable references to `s` without an error,
+by putting the first one in a block of its own:",
+        working: "scoped_borrows",
+    },
+    Hints {
+        example: "shared-then-mutable",
+        nudge: "\
+Look at where `r1` and `r2` are used for the last time, and where `r3`
+is taken.",
+        concept: "\
+A `&mut` reference cannot be taken while `&` references to the same
+value are still going to be used. Once `r1` and `r2` have been used for
+the last time, `s` can be borrowed mutably.",
+        solution: "\
+`shared_and_mutable` takes `r3` only after the `println!` that uses `r1`
+and `r2` for the last time:",
+        working: "shared_and_mutable",
+    },
+    Hints {
+        example: "dangle",
+        nudge: "\
+Look at what `dangle` returns, and at what happens to `s` when `dangle`
+returns.",
+        concept: "\
+`s` is dropped at the end of `dangle`, so a reference to it would point
+at nothing. Instead of lending `s` to the caller, give it away: return
+the `String` itself and ownership moves out of the function.",
+        solution: "\
+`no_dangle()` does the same thing correctly by returning `String`
+rather than `&String`:",
+        working: "no_dangle",
+    },
+    Hints {
+        example: "immutable-change",
+        nudge: "\
+Look at the type of `change`'s parameter, and at what `change` does
+with it.",
+        concept: "\
+`push_str` changes the `String`, and a `&` reference only allows reading
+it. Changing a value through a reference needs a `&mut` reference, and
+the caller has to pass one.",
+        solution: "\
+`change(some_string: &mut String)` in `references.rs` takes a mutable
+reference, and `mutable_references` passes it `&mut s`:",
+        working: "change",
+    },
+];
+
+/// The hints for an exercise, from the nudge to the near solution, or
+/// none if the exercise has no hints
+pub fn for_exercise(id: &str) -> Result<Vec<Hint>, Error> {
+    let hints = match HINTS.iter().find(|hints| hints.example == id) {
+        Some(hints) => hints,
+        None => return Ok(Vec::new()),
+    };
+
+    let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut solution = format!("{}\n\n", hints.solution);
+    if let Some((start, end)) = source::definition_lines(&text, hints.working) {
+        // The commented-out error inside the working code is the exercise
+        // itself, so it is left out
+        for line in lines[start..=end]
+            .iter()
+            .filter(|line| !line.contains("@compile_fail"))
+        {
+            solution.push_str(format!("    {}", line).trim_end());
+            solution.push('\n');
+        }
+    }
+
+    Ok(vec![
+        Hint {
+            level: Level::Nudge,
+            text: format!("{}\n", hints.nudge),
+        },
+        Hint {
+            level: Level::Concept,
+            text: format!("{}\n", hints.concept),
+        },
+        Hint {
+            level: Level::NearSolution,
+            text: solution,
+        },
+    ])
+}
+
+/// How many hint levels this many failed checks unlock
+pub fn unlocked(failures: usize) -> usize {
+    LEVELS
+        .iter()
+        .take_while(|level| failures >= level.failures_needed())
+        .count()
+}
+
+/// The student checks and hints are recorded for: `REFERENCES_STUDENT`,
+/// or else the name of the user running the program
+pub fn student() -> String {
+    ["REFERENCES_STUDENT", "USER", "USERNAME"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .map(|name| name.trim().replace('\t', " "))
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("student"))
+}
+
+/// One student's attempts at one exercise
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub student: String,
+    pub exercise: String,
+    pub failures: usize,
+    /// How many hint levels the student has looked at
+    pub hints_used: usize,
+}
+
+/// The records of every student working in one exercise directory, kept
+/// one per line as `student<TAB>exercise<TAB>failures<TAB>hints used`
+pub struct Log {
+    path: PathBuf,
+    pub records: Vec<Record>,
+}
+
+impl Log {
+    /// The log for the exercise directory `dir`, empty if nothing has
+    /// been recorded there yet
+    pub fn load(dir: &Path) -> io::Result<Log> {
+        let path = dir.join(RECORD_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        let records = text
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                match fields[..] {
+                    [student, exercise, failures, hints_used] => Some(Record {
+                        student: student.to_string(),
+                        exercise: exercise.to_string(),
+                        failures: failures.parse().ok()?,
+                        hints_used: hints_used.parse().ok()?,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Ok(Log { path, records })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut text = String::new();
+        for record in &self.records {
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                record.student, record.exercise, record.failures, record.hints_used
+            ));
+        }
+
+        fs::write(&self.path, text)
+    }
+
+    /// The record of `student` working on `exercise`, added if there is
+    /// none yet
+    pub fn record(&mut self, student: &str, exercise: &str) -> &mut Record {
+        let position = self
+            .records
+            .iter()
+            .position(|r| r.student == student && r.exercise == exercise);
+
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.records.push(Record {
+                    student: student.to_string(),
+                    exercise: exercise.to_string(),
+                    failures: 0,
+                    hints_used: 0,
+                });
+                self.records.len() - 1
+            }
+        };
+
+        &mut self.records[index]
+    }
+}
@@ -0,0 +1,175 @@
+// Project: references
+// Author: Greg Folker
+
+// The lessons from `references.rs` again, printing the real addresses and
+// sizes of the values and references involved. A reference is only the
+// address of the value it refers to, which is what lets it refer to a
+// value without taking ownership of it. Each one follows the lesson of
+// the same name
+
+// Borrows are of the `String` itself, as in `references.rs`
+#![allow(clippy::ptr_arg)]
+
+use std::mem;
+use std::ptr;
+
+fn log(message: &str) {
+    println!("[inspect] {}", message);
+}
+
+// `&String` is a thin pointer, only an address. `&str` is a fat pointer,
+// an address and a length, because a `str` does not know its own length
+fn sizes() {
+    log(&format!(
+        "size_of::<String>() = {}: a pointer to the heap, a length and a capacity",
+        mem::size_of::<String>()
+    ));
+    log(&format!(
+        "size_of::<&String>() = {}: only the address of the `String`",
+        mem::size_of::<&String>()
+    ));
+    log(&format!(
+        "size_of::<&&String>() = {}: only the address of the `&String`",
+        mem::size_of::<&&String>()
+    ));
+    log(&format!(
+        "size_of::<&str>() = {}: a fat pointer, the address of the text and its length",
+        mem::size_of::<&str>()
+    ));
+}
+
+pub fn borrowing() {
+    let s1 = String::from("Hello");
+    let r = &s1;
+    let rr = &r;
+
+    log(&format!("`s1` lives at {:p}", &s1));
+    log(&format!("`&s1` is the address {:p}, the same place", r));
+    log(&format!(
+        "`&&s1` is the address {:p}, where the reference itself lives, \
+         and it holds {:p}",
+        rr, *rr
+    ));
+    log(&format!(
+        "the text of `s1` is on the heap at {:p}",
+        s1.as_ptr()
+    ));
+    sizes();
+
+    let len = calculate_length(&s1);
+
+    println!("The length of '{}' is {}", s1, len);
+}
+
+pub fn mutable_references() {
+    let mut s = String::from("Hello");
+
+    log(&format!("`s` lives at {:p}", &s));
+    log(&format!(
+        "size_of::<&mut String>() = {}, the same as a `&String`",
+        mem::size_of::<&mut String>()
+    ));
+
+    change(&mut s);
+
+    println!("{}", s);
+}
+
+pub fn mutable_aliasing() {
+    let mut s = String::from("Hello");
+    let address: *const String = &s;
+
+    let r1 = &mut s;
+    log(&format!(
+        "`r1` holds {:p}, the address of `s` ({:p})",
+        r1, address
+    ));
+
+    println!("{}", r1);
+}
+
+pub fn scoped_borrows() {
+    let mut s = String::from("Hello");
+    let address: *const String = &s;
+
+    {
+        let r1 = &mut s;
+        log(&format!("`r1` holds {:p}", r1));
+        println!("{}", r1);
+    }
+
+    let r2 = &mut s;
+    log(&format!(
+        "`r2` holds {:p}, the same address `r1` held, since `s` ({:p}) has not moved",
+        r2, address
+    ));
+    println!("{}", r2);
+}
+
+pub fn shared_and_mutable() {
+    let mut s = String::from("Hello");
+
+    let r1 = &s;
+    let r2 = &s;
+
+    log(&format!("`s` lives at {:p}", &s));
+    log(&format!("`r1` holds {:p}", r1));
+    log(&format!("`r2` holds {:p}", r2));
+    log(&format!(
+        "ptr::eq(r1, r2) = {}: both point at the same `String`, nothing was copied",
+        ptr::eq(r1, r2)
+    ));
+    log(&format!(
+        "but `r1` and `r2` are separate variables, at {:p} and {:p}",
+        &r1, &r2
+    ));
+
+    println!("r1={} and r2={}", r1, r2);
+
+    let r3 = &mut s;
+    log(&format!("`r3` holds {:p}, the same address again", r3));
+    println!("r3={}", r3);
+}
+
+pub fn dangling_references() {
+    let reference_to_something = no_dangle();
+
+    log(&format!(
+        "`reference_to_something` lives at {:p}, and its text is still at {:p}",
+        &reference_to_something,
+        reference_to_something.as_ptr()
+    ));
+
+    println!("reference_to_something={}", reference_to_something);
+}
+
+fn calculate_length(s: &String) -> usize {
+    log(&format!(
+        "in `calculate_length`, `s` lives at {:p} and holds {:p}, the address of `s1`",
+        &s, s
+    ));
+
+    s.len()
+}
+
+fn change(some_string: &mut String) {
+    log(&format!(
+        "in `change`, `some_string` holds {:p}, the address of `s`",
+        some_string
+    ));
+
+    some_string.push_str(", world");
+}
+
+fn no_dangle() -> String {
+    let s = String::from("Hello");
+
+    log(&format!(
+        "in `no_dangle`, `s` lives at {:p} and its text is at {:p}",
+        &s,
+        s.as_ptr()
+    ));
+    log("returning `s` moves the `String` to the caller, the text stays where it is");
+
+    s
+}
@@ -0,0 +1,269 @@
+// Project: references
+// Author: Greg Folker
+
+// Just enough JSON to read what `rustc --error-format=json` prints, one
+// value per line
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Members in the order they were written
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    /// Byte offset in the text where parsing failed
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid JSON at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Value {
+    /// The member `key` of an object, or `Null` if there is none
+    pub fn get(&self, key: &str) -> &Value {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&Value::Null, |(_, v)| v),
+            _ => &Value::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    /// The elements of an array, or none for anything else
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, Error> {
+    let mut parser = Parser { text, offset: 0 };
+
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.offset < text.len() {
+        return Err(parser.error("unexpected text after the value"));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        self.whitespace();
+        match self.next() {
+            Some(next) if next == c => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", c))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, Error> {
+        if self.text[self.offset..].starts_with(word) {
+            self.offset += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some('-' | '0'..='9') => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, Error> {
+        let mut members = Vec::new();
+        self.expect('{')?;
+        self.whitespace();
+
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a member name"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+
+            self.whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        let mut items = Vec::new();
+        self.expect('[')?;
+        self.whitespace();
+
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let mut out = String::new();
+        self.expect('"')?;
+
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(out),
+                Some('\\') => match self.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('/') => out.push('/'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('u') => out.push(self.unicode()?),
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    // The character after `\u`, which may be a pair of UTF-16 surrogates
+    fn unicode(&mut self) -> Result<char, Error> {
+        let first = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if !self.text[self.offset..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.offset += 2;
+            let second = self.hex4()?;
+            0x10000 + ((first - 0xd800) << 10) + (second.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            first
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, Error> {
+        let digits = self
+            .text
+            .get(self.offset..self.offset + 4)
+            .ok_or_else(|| self.error("expected four hex digits"))?;
+        let value =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("expected four hex digits"))?;
+
+        self.offset += 4;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.offset;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.offset += 1;
+        }
+
+        self.text[start..self.offset]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| Error {
+                offset: start,
+                message: String::from("invalid number"),
+            })
+    }
+}
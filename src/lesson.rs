@@ -0,0 +1,224 @@
+// Project: references
+// Author: Greg Folker
+
+use crate::anchors;
+use crate::examples::{self, Example};
+use crate::inspected;
+use crate::memory::{self, Diagram};
+use crate::references;
+use crate::source::{self, Error};
+use crate::traced;
+
+/// A single demonstration from `references.rs` that can be listed
+/// and run on its own
+pub struct Lesson {
+    pub id: &'static str,
+    pub title: &'static str,
+    /// Ids of the lessons that should be understood before this one
+    pub prerequisites: &'static [&'static str],
+    pub run: fn(),
+    /// The same lesson using `trace::Traced`, printing when each value is
+    /// created, borrowed, moved and dropped
+    pub traced: Option<fn()>,
+    /// The same lesson printing the real addresses and sizes of its
+    /// values and references
+    pub inspected: Option<fn()>,
+    /// The same lesson taking pictures of the stack and heap as it runs
+    pub memory: Option<fn() -> Vec<Diagram>>,
+    pub narration: &'static str,
+    /// Name of the function in `references.rs` that `run` points at
+    pub function: &'static str,
+    /// Functions the lesson calls, or would call if it compiled, which
+    /// are shown alongside it
+    pub helpers: &'static [&'static str],
+}
+
+impl Lesson {
+    pub fn prerequisites(&self) -> impl Iterator<Item = &'static Lesson> + '_ {
+        self.prerequisites.iter().filter_map(|id| find(id))
+    }
+
+    /// The lesson's code as written in `references.rs`, followed by the
+    /// helper functions it uses. Each line starts with its line number in
+    /// `references.rs`, which is what the commentary's line references
+    /// point at, and the `@compile_fail` markers are left out
+    pub fn source(&self) -> Result<String, Error> {
+        let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines.len().to_string().len();
+
+        let mut ranges = source::item_lines(&text, self.function);
+        for helper in self.helpers {
+            ranges.extend(source::item_lines(&text, helper));
+        }
+
+        let items: Vec<String> = ranges
+            .into_iter()
+            .map(|(start, end)| {
+                let numbered: Vec<String> = (start..=end)
+                    .filter(|&n| !lines[n].contains(examples::MARKER))
+                    .map(|n| format!("{:>width$} | {}", n + 1, lines[n], width = width))
+                    .map(|line| line.trim_end().to_string())
+                    .collect();
+                numbered.join("\n")
+            })
+            .collect();
+
+        Ok(items.join("\n\n"))
+    }
+
+    /// The lesson's code as the `main` function of a program of its own
+    pub fn as_main(&self) -> Result<String, Error> {
+        let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)?;
+        let lines: Vec<&str> = text.lines().collect();
+
+        let mut main = vec!["fn main() {"];
+        if let Some((start, end)) = source::definition_lines(&text, self.function) {
+            main.extend(&lines[start + 1..=end]);
+        }
+
+        Ok(main.join("\n"))
+    }
+
+    /// The lesson as a complete program: its code as `main`, followed by
+    /// the helper functions it calls. Helpers that are commented out, such
+    /// as `dangle`, are left out so that the program compiles
+    pub fn program(&self) -> Result<String, Error> {
+        let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)?;
+        let lines: Vec<&str> = text.lines().collect();
+
+        let mut items = vec![self.as_main()?];
+        for helper in self.helpers {
+            if let Some((start, end)) = source::definition_lines(&text, helper) {
+                items.push(lines[start..=end].join("\n"));
+            }
+        }
+
+        Ok(items.join("\n\n") + "\n")
+    }
+
+    /// The narration with any anchors in it resolved to line numbers in
+    /// `references.rs`
+    pub fn resolved_narration(&self) -> Result<String, Error> {
+        anchors::resolve_in(source::REFERENCES_PATH, self.narration).map_err(|e| Error {
+            message: format!("narration of '{}': {}", self.id, e.message),
+            ..e
+        })
+    }
+}
+
+static LESSONS: &[Lesson] = &[
+    Lesson {
+        id: "borrowing",
+        title: "Borrowing a value with a reference",
+        prerequisites: &[],
+        run: references::borrowing,
+        traced: Some(traced::borrowing),
+        inspected: Some(inspected::borrowing),
+        memory: Some(memory::lessons::borrowing),
+        narration: "\
+`calculate_length` takes `&String`, a reference to `s1`, instead of the
+`String` itself. Ownership never moves, so `s1` is still usable after
+the call and nothing is dropped when the function returns.",
+        function: "borrowing",
+        helpers: &["calculate_length"],
+    },
+    Lesson {
+        id: "mutable-references",
+        title: "Changing a borrowed value with `&mut`",
+        prerequisites: &["borrowing"],
+        run: references::mutable_references,
+        traced: Some(traced::mutable_references),
+        inspected: Some(inspected::mutable_references),
+        memory: Some(memory::lessons::mutable_references),
+        narration: "\
+Passing `&mut s` lets `change` modify the caller's `String` in place.
+Both the variable and the parameter have to be declared mutable, so a
+function can never change a value behind the caller's back.",
+        function: "mutable_references",
+        helpers: &["change"],
+    },
+    Lesson {
+        id: "mutable-aliasing",
+        title: "Only one mutable reference at a time",
+        prerequisites: &["mutable-references"],
+        run: references::mutable_aliasing,
+        traced: Some(traced::mutable_aliasing),
+        inspected: Some(inspected::mutable_aliasing),
+        memory: Some(memory::lessons::mutable_aliasing),
+        narration: "\
+While `r1` is a live `&mut s`, no other reference to `s` may exist.
+Taking a second `&mut s` is rejected at compile time, which is how Rust
+rules out data races before the program ever runs.",
+        function: "mutable_aliasing",
+        helpers: &[],
+    },
+    Lesson {
+        id: "scoped-borrows",
+        title: "Ending a mutable borrow with a new scope",
+        prerequisites: &["mutable-aliasing"],
+        run: references::scoped_borrows,
+        traced: Some(traced::scoped_borrows),
+        inspected: Some(inspected::scoped_borrows),
+        memory: Some(memory::lessons::scoped_borrows),
+        narration: "\
+Curly brackets introduce a new scope. Once the first `r1` goes out of
+scope at the closing bracket, a new `&mut s` can be taken without
+conflicting with it.",
+        function: "scoped_borrows",
+        helpers: &[],
+    },
+    Lesson {
+        id: "shared-and-mutable",
+        title: "Mixing shared and mutable references",
+        prerequisites: &["mutable-aliasing"],
+        run: references::shared_and_mutable,
+        traced: Some(traced::shared_and_mutable),
+        inspected: Some(inspected::shared_and_mutable),
+        memory: Some(memory::lessons::shared_and_mutable),
+        narration: "\
+Any number of `&s` may coexist, but not alongside a `&mut s`. A borrow
+ends after its last use rather than at the end of the block, so `r3` is
+allowed once `r1` and `r2` have been printed on line {@last-shared-use}.",
+        function: "shared_and_mutable",
+        helpers: &[],
+    },
+    Lesson {
+        id: "dangling-references",
+        title: "Dangling references are rejected",
+        prerequisites: &["borrowing"],
+        run: references::dangling_references,
+        traced: Some(traced::dangling_references),
+        inspected: Some(inspected::dangling_references),
+        memory: Some(memory::lessons::dangling_references),
+        narration: "\
+`dangle` would return a reference to a `String` that is dropped when
+the function returns. The compiler refuses it, and `no_dangle` returns
+the `String` itself so ownership moves out to the caller instead.",
+        function: "dangling_references",
+        helpers: &["dangle", "no_dangle"],
+    },
+];
+
+/// Every lesson, in the order they are meant to be taught
+pub fn all() -> &'static [Lesson] {
+    LESSONS
+}
+
+/// Looks a lesson up by id, also accepting its title-style spelling
+/// such as "mutable aliasing" or "mutable_aliasing"
+pub fn find(id: &str) -> Option<&'static Lesson> {
+    let id = id.trim().to_lowercase().replace([' ', '_'], "-");
+
+    LESSONS.iter().find(|lesson| lesson.id == id)
+}
+
+/// The lesson whose code, or one of whose helpers, holds `example`
+pub fn for_example(example: &Example) -> Option<&'static Lesson> {
+    let teaches =
+        |lesson: &Lesson, name: &str| lesson.function == name || lesson.helpers.contains(&name);
+
+    LESSONS
+        .iter()
+        .find(|lesson| example.functions.iter().any(|name| teaches(lesson, name)))
+}
@@ -0,0 +1,31 @@
+// Project: references
+// Author: Greg Folker
+
+pub mod anchors;
+pub mod c;
+pub mod cache;
+pub mod catalog;
+pub mod compile;
+pub mod diagnose;
+pub mod editions;
+pub mod examples;
+pub mod exercise;
+pub mod hints;
+pub mod inspected;
+pub mod json;
+pub mod lesson;
+pub mod memory;
+pub mod progress;
+pub mod puzzle;
+pub mod quiz;
+pub mod references;
+pub mod sandbox;
+pub mod snapshot;
+pub mod source;
+pub mod timeline;
+pub mod toy;
+pub mod trace;
+pub mod traced;
+pub mod watch;
+
+mod tempdir;
@@ -1,125 +1,1414 @@
 // Project: references
 // Author: Greg Folker
 
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use references::c;
+use references::cache::Cache;
+use references::catalog;
+use references::compile;
+use references::diagnose;
+use references::editions::{self, Snippet};
+use references::examples::{self, Example};
+use references::exercise;
+use references::hints;
+use references::lesson::{self, Lesson};
+use references::memory;
+use references::progress::{self, Progress};
+use references::puzzle;
+use references::quiz::{self, Kind};
+use references::snapshot::{self, Status};
+use references::source;
+use references::timeline;
+use references::toy::{self, Program};
+use references::watch::Watcher;
+
+const USAGE: &str = "\
+Usage: references [COMMAND] [ARGS...]
+
+Commands:
+    list               List every lesson
+    run [--trace|--inspect] [LESSON...]
+                       Run the given lessons, or every lesson if none are given.
+                       `--trace` prints when each value is created, borrowed,
+                       moved and dropped, `--inspect` prints the addresses and
+                       sizes of values and the references to them
+    show LESSON        Print the source of a lesson along with its commentary
+    explain LESSON     Print what a lesson is meant to teach
+    memory [--format ascii|dot|svg] LESSON
+                       Draw the stack and heap at the key moments of a lesson,
+                       using the addresses of its values as it runs
+    timeline LESSON    Draw when each reference in a lesson is taken, last used
+                       and dead
+    toy [--explain] [PROGRAM|FILE]
+                       Check and run a program in the toy ownership language,
+                       or list the bundled programs if none is given.
+                       `--explain` prints every step of the borrow check
+    step PROGRAM|FILE  Run a toy program one statement at a time, showing what
+                       each variable owns or borrows and what was dropped
+    exercise [EXERCISE] [DIR]
+                       Write an exercise out for you to fix, by default to
+                       exercises/, or list the exercises if none is given
+    check FILE         Grade your fix of an exercise: it has to compile, print
+                       the right output and avoid shortcuts such as `.clone()`
+    quiz [LESSON...]   Answer questions about the given lessons, or every
+                       lesson: does this compile, which error does it give,
+                       what does it print. Answers are checked with `rustc`
+                       and the recorded output, and scores are kept
+    puzzle [SEED]      Make up a borrow puzzle and ask whether it compiles,
+                       checking the answer with `rustc`. The same seed makes
+                       the same puzzle again
+    progress           Show which lessons you have run and which exercises you
+                       have passed, with your attempts and when you started
+    reset              Forget all of your recorded progress
+    watch EXERCISE|FILE
+                       Check your fix of an exercise again every time you
+                       save it, showing which checks pass, the lessons behind
+                       any compiler errors and when the next hint unlocks.
+                       Saves do not count as attempts or towards hints
+    hint FILE          Show the hints for an exercise that repeated failed
+                       checks have unlocked, from a nudge to the working code
+    errors [CODE|KEYWORD]
+                       Explain a borrowing error code, along with what
+                       `rustc --explain` says about it, or list the codes that
+                       mention a keyword. Lists every code if none is given
+    diagnose FILE      Compile a Rust file of your own and explain each error,
+                       along with the lesson that demonstrates it
+    whatif [EXAMPLE]   Uncomment an error example and show what `rustc` says
+                       about it, or list the examples if none is given
+    c [PROGRAM]        Compile and run the C version of a lesson function,
+                       with AddressSanitizer if the C compiler has it, and
+                       show it next to the Rust version. Lists the programs
+                       if none is given
+    editions LESSON|EXAMPLE
+                       Compile a lesson and its error examples, or a single
+                       example, as the 2015, 2018 and 2021 editions and show
+                       what `rustc` says about each side by side
+    snapshot [--bless] [LESSON...]
+                       Compare the output of the given lessons, or of every
+                       lesson, with their recorded output. `--bless` records
+                       the current output instead
+    fixtures [DIR]     Write every commented-out error example out as a
+                       compile-fail fixture, by default to fixtures/compile-fail
+    help               Print this message
+
+Running `references` without a command runs every lesson in order.
+Checks and hints are recorded for the student named by
+`REFERENCES_STUDENT`, or else for the current user. Progress is kept in
+the user's data directory, or in `REFERENCES_DATA_DIR` if it is set.
+Lessons can be named by id (`mutable-aliasing`) or by title-style
+spelling (`\"mutable aliasing\"`).
+";
+
+enum Error {
+    // Bad command line, reported along with the usage text
+    Usage(String),
+    // The command was understood but could not be carried out
+    Failed(String),
+}
+
 fn main() {
-	println!("Hello, World!");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(e) = dispatch(&args) {
+        let code = match e {
+            Error::Usage(message) => {
+                eprintln!("error: {}\n\n{}", message, USAGE);
+                2
+            }
+            Error::Failed(message) => {
+                eprintln!("error: {}", message);
+                1
+            }
+        };
+
+        process::exit(code);
+    }
+}
+
+fn dispatch(args: &[String]) -> Result<(), Error> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => {
+            println!("Hello, World!");
+            return run(&[]);
+        }
+    };
+
+    match command {
+        "list" => list(),
+        "run" => run(rest),
+        "show" => show(single_lesson(command, rest)?),
+        "explain" => explain(single_lesson(command, rest)?),
+        "timeline" => {
+            let lesson = single_lesson(command, rest)?;
+            let timeline = timeline::render(lesson).map_err(|e| Error::Failed(e.to_string()))?;
+            print!("{}", timeline);
+            Ok(())
+        }
+        "memory" => memory(rest),
+        "toy" => toy(rest),
+        "step" => match rest {
+            [name] => {
+                let program = checked_toy(name, false)?;
+                let steps = program.steps().map_err(Error::Failed)?;
+                print!("{}", toy::step::render(&steps));
+                Ok(())
+            }
+            _ => Err(Error::Usage(String::from("`step` takes a single program"))),
+        },
+        "whatif" => match rest {
+            [] => list_examples(),
+            [id] => whatif(&find_example(id)?),
+            _ => Err(Error::Usage(String::from(
+                "`whatif` takes a single example",
+            ))),
+        },
+        "c" => match rest {
+            [] => list_c_programs(),
+            [name] => compare_c(name),
+            _ => Err(Error::Usage(String::from("`c` takes a single program"))),
+        },
+        "editions" => match rest {
+            [id] => compare_editions(id),
+            _ => Err(Error::Usage(String::from(
+                "`editions` takes a single lesson or example",
+            ))),
+        },
+        "exercise" => match rest {
+            [] => list_exercises(),
+            [id] => write_exercise(id, Path::new(exercise::WORKSPACE)),
+            [id, dir] => write_exercise(id, Path::new(dir)),
+            _ => Err(Error::Usage(String::from(
+                "`exercise` takes an exercise and a directory",
+            ))),
+        },
+        "check" => match rest {
+            [file] => check(Path::new(file)),
+            _ => Err(Error::Usage(String::from("`check` takes a single file"))),
+        },
+        "quiz" => quiz(rest),
+        "puzzle" => match rest {
+            [] => solve_puzzle(puzzle::random_seed()),
+            [seed] => match seed.parse() {
+                Ok(seed) => solve_puzzle(seed),
+                Err(_) => Err(Error::Usage(format!("'{}' is not a seed", seed))),
+            },
+            _ => Err(Error::Usage(String::from("`puzzle` takes a single seed"))),
+        },
+        "progress" => match rest {
+            [] => show_progress(),
+            _ => Err(Error::Usage(String::from("`progress` takes no arguments"))),
+        },
+        "reset" => match rest {
+            [] => reset(),
+            _ => Err(Error::Usage(String::from("`reset` takes no arguments"))),
+        },
+        "watch" => match rest {
+            [target] => watch(target),
+            _ => Err(Error::Usage(String::from(
+                "`watch` takes a single exercise or file",
+            ))),
+        },
+        "hint" => match rest {
+            [file] => hint(Path::new(file)),
+            _ => Err(Error::Usage(String::from("`hint` takes a single file"))),
+        },
+        "errors" => match rest {
+            [] => list_errors(catalog::all().iter().collect()),
+            [query] => errors(query),
+            _ => Err(Error::Usage(String::from(
+                "`errors` takes a single code or keyword",
+            ))),
+        },
+        "diagnose" => match rest {
+            [file] => diagnose(Path::new(file)),
+            _ => Err(Error::Usage(String::from("`diagnose` takes a single file"))),
+        },
+        "snapshot" => snapshots(rest),
+        "fixtures" => match rest {
+            [] => fixtures(Path::new("fixtures/compile-fail")),
+            [dir] => fixtures(Path::new(dir)),
+            _ => Err(Error::Usage(String::from(
+                "`fixtures` takes a single directory",
+            ))),
+        },
+        "help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(Error::Usage(format!("unknown command '{}'", command))),
+    }
+}
+
+fn find_lesson(id: &str) -> Result<&'static Lesson, Error> {
+    lesson::find(id).ok_or_else(|| {
+        Error::Failed(format!(
+            "unknown lesson '{}', see `references list` for the available lessons",
+            id
+        ))
+    })
+}
+
+fn find_example(id: &str) -> Result<Example, Error> {
+    examples::find(id)
+        .map_err(|e| Error::Failed(e.to_string()))?
+        .ok_or_else(|| {
+            Error::Failed(format!(
+                "unknown example '{}', see `references whatif` for the available examples",
+                id
+            ))
+        })
+}
+
+fn single_lesson(command: &str, args: &[String]) -> Result<&'static Lesson, Error> {
+    match args {
+        [id] => find_lesson(id),
+        [] => Err(Error::Usage(format!("`{}` needs a lesson", command))),
+        _ => Err(Error::Usage(format!("`{}` takes a single lesson", command))),
+    }
+}
+
+fn list() -> Result<(), Error> {
+    let width = lesson::all().iter().map(|l| l.id.len()).max().unwrap_or(0);
+
+    for lesson in lesson::all() {
+        println!("{:width$}  {}", lesson.id, lesson.title, width = width);
+    }
+
+    Ok(())
+}
+
+// Looks every lesson up before doing anything with them so a typo at the
+// end of the list does not leave half of the output behind
+fn lessons_or_all(ids: &[String]) -> Result<Vec<&'static Lesson>, Error> {
+    if ids.is_empty() {
+        Ok(lesson::all().iter().collect())
+    } else {
+        ids.iter().map(|id| find_lesson(id)).collect()
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    let trace = args.iter().any(|arg| arg == "--trace");
+    let inspect = args.iter().any(|arg| arg == "--inspect");
+    let ids: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--trace" && *arg != "--inspect")
+        .cloned()
+        .collect();
+
+    if let Some(flag) = ids.iter().find(|id| id.starts_with("--")) {
+        return Err(Error::Usage(format!("unknown option '{}' for `run`", flag)));
+    }
+    if trace && inspect {
+        return Err(Error::Usage(String::from(
+            "`--trace` and `--inspect` cannot be used together",
+        )));
+    }
+
+    for lesson in lessons_or_all(&ids)? {
+        match (lesson.traced, lesson.inspected) {
+            (Some(traced), _) if trace => traced(),
+            (_, Some(inspected)) if inspect => inspected(),
+            _ => (lesson.run)(),
+        }
+        record_progress(|progress, now| progress.lesson_run(lesson.id, now));
+    }
+
+    Ok(())
+}
+
+fn show(lesson: &Lesson) -> Result<(), Error> {
+    println!("// {}\n", lesson.title);
+    let source = lesson.source().map_err(|e| Error::Failed(e.to_string()))?;
+    println!("{}", source);
+
+    Ok(())
+}
+
+fn explain(lesson: &Lesson) -> Result<(), Error> {
+    println!("{}\n", lesson.title);
+    let narration = lesson
+        .resolved_narration()
+        .map_err(|e| Error::Failed(e.to_string()))?;
+    println!("{}", narration);
+
+    let prerequisites: Vec<&str> = lesson.prerequisites().map(|l| l.id).collect();
+    if !prerequisites.is_empty() {
+        println!("\nBuilds on: {}", prerequisites.join(", "));
+    }
+
+    Ok(())
+}
+
+fn list_examples() -> Result<(), Error> {
+    let examples = examples::all().map_err(|e| Error::Failed(e.to_string()))?;
+    let width = examples.iter().map(|e| e.id.len()).max().unwrap_or(0);
+
+    for example in examples {
+        println!(
+            "{:width$}  {}  {} {}",
+            example.id,
+            example.code,
+            example.file,
+            line_list(&example.lines),
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+fn whatif(example: &Example) -> Result<(), Error> {
+    let failed = |e: io::Error| Error::Failed(format!("could not run rustc: {}", e));
+
+    match lesson::for_example(example) {
+        Some(lesson) => println!("{} ({})\n", lesson.title, lesson.id),
+        None => println!("{}\n", example.id),
+    }
+    println!("{}\n", example.commentary);
+
+    println!(
+        "Uncommenting {} {}:\n",
+        example.file,
+        line_list(&example.lines)
+    );
+    for line in example.code_lines.lines() {
+        if line.is_empty() {
+            println!();
+        } else {
+            println!("    {}", line);
+        }
+    }
+
+    let outcome = compile::compile(&example.id, &example.source).map_err(failed)?;
+    let version = compile::version().map_err(failed)?;
+
+    if outcome.success {
+        println!("\n{} compiles it without an error", version);
+    } else {
+        println!("\n{} says:\n", version);
+        print!("{}", outcome.stderr);
+    }
+
+    if !outcome.codes.contains(&example.code) {
+        println!(
+            "\nnote: the lesson expects this to fail with {}, which rustc did not report",
+            example.code
+        );
+    }
+
+    Ok(())
+}
+
+fn list_c_programs() -> Result<(), Error> {
+    let width = c::all().iter().map(|p| p.name.len()).max().unwrap_or(0);
+
+    for program in c::all() {
+        println!(
+            "{:width$}  {}  next to `{}` in {}",
+            program.name,
+            program.file,
+            program.function,
+            program.lesson,
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+fn compare_c(name: &str) -> Result<(), Error> {
+    let program = c::find(name).ok_or_else(|| {
+        Error::Failed(format!(
+            "unknown C program '{}', see `references c` for the available programs",
+            name
+        ))
+    })?;
+    let lesson = find_lesson(program.lesson)?;
+    let failed = |e: io::Error| Error::Failed(format!("could not run {}: {}", c::compiler(), e));
+
+    println!("{} ({})\n", lesson.title, lesson.id);
+    println!("In C, {}:\n", program.file);
+    print!("{}", indented(program.source));
+
+    let sanitized = c::sanitizer_available().map_err(failed)?;
+    let outcome = program.run(sanitized).map_err(failed)?;
+    println!("\n`{}` says:\n", program.command(sanitized));
+    if outcome.compile.stderr.trim().is_empty() {
+        println!("    nothing");
+    } else {
+        print!("{}", indented(&outcome.compile.stderr));
+    }
+
+    match &outcome.run {
+        Some(run) => {
+            println!("\nRun, it {}, printing:\n", run.exit);
+            print!("{}", indented(&run.stdout));
+            let report = c::report(&run.stderr);
+            if !report.trim().is_empty() {
+                print!("{}", indented(&report));
+            }
+        }
+        None => println!("\nIt did not compile, so it was not run"),
+    }
+    if !outcome.sanitized {
+        println!(
+            "\nnote: {} has no AddressSanitizer here, so nothing checks what the \
+             program does with its pointers",
+            c::compiler()
+        );
+    }
+
+    let failed = |e: io::Error| Error::Failed(format!("could not run rustc: {}", e));
+    println!("\nIn Rust, {}:\n", source::REFERENCES_PATH);
+    match program.example {
+        Some(id) => {
+            let example = find_example(id)?;
+            print!("{}", indented(&example.code_lines));
+            let outcome = compile::compile(&example.id, &example.source).map_err(failed)?;
+            let version = compile::version().map_err(failed)?;
+            println!("\n{} rejects it before it can run:\n", version);
+            print!("{}", indented(&outcome.stderr));
+        }
+        None => {
+            let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)
+                .map_err(|e| Error::Failed(e.to_string()))?;
+            // The marker would give away the error the commented-out
+            // version fails with
+            let items = source::items(&text, program.function).join("\n\n");
+            let lines: Vec<&str> = items
+                .lines()
+                .filter(|line| !line.contains(examples::MARKER))
+                .collect();
+            print!("{}", indented(&lines.join("\n")));
+            if let Some(expected) = snapshot::expected(lesson) {
+                println!("\nThe {} lesson prints:\n", lesson.id);
+                print!("{}", indented(expected));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// `text` indented by four spaces, leaving blank lines blank
+fn indented(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::from("\n")
+            } else {
+                format!("    {}\n", line)
+            }
+        })
+        .collect()
+}
+
+fn compare_editions(id: &str) -> Result<(), Error> {
+    let snippets = match lesson::find(id) {
+        Some(lesson) => {
+            println!("{} ({})", lesson.title, lesson.id);
+            editions::lesson_snippets(lesson).map_err(|e| Error::Failed(e.to_string()))?
+        }
+        None => {
+            let example = find_example(id)?;
+            println!("{}", example.id);
+            vec![Snippet {
+                name: example.id,
+                source: example.source,
+            }]
+        }
+    };
+
+    let failed = |e: io::Error| Error::Failed(format!("could not run rustc: {}", e));
+    let comparisons = editions::compare(&snippets, &Cache::open()).map_err(failed)?;
+    let version = compile::version().map_err(failed)?;
+    println!("as compiled by {}\n", version);
+
+    let width = comparisons.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let column = comparisons
+        .iter()
+        .flat_map(|c| c.outcomes.iter().map(|o| editions::verdict(o).len()))
+        .chain(editions::EDITIONS.iter().map(|e| e.len()))
+        .max()
+        .unwrap_or(0);
+    let row = |name: &str, cells: Vec<String>| {
+        let mut line = format!("{:width$}", name, width = width);
+        for cell in cells {
+            line.push_str(&format!("  {:column$}", cell, column = column));
+        }
+        println!("{}", line.trim_end());
+    };
+    row(
+        "",
+        editions::EDITIONS.iter().map(|e| e.to_string()).collect(),
+    );
+    for comparison in &comparisons {
+        row(
+            &comparison.name,
+            comparison.outcomes.iter().map(editions::verdict).collect(),
+        );
+    }
+
+    for comparison in &comparisons {
+        if comparison.diagnostics_agree() {
+            let stderr = &comparison.outcomes[0].stderr;
+            if !stderr.trim().is_empty() {
+                println!("\n{} says the same as every edition:\n", comparison.name);
+                print!("{}", stderr);
+            }
+            continue;
+        }
+        for (edition, outcome) in editions::EDITIONS.iter().zip(&comparison.outcomes) {
+            println!("\n{} as {}:\n", comparison.name, edition);
+            if outcome.stderr.trim().is_empty() {
+                println!("(no diagnostics)");
+            } else {
+                print!("{}", outcome.stderr);
+            }
+        }
+    }
+
+    if comparisons.iter().all(|c| c.verdicts_agree()) {
+        println!(
+            "\nnote: every edition gives the same verdicts. Non-lexical lifetimes have \
+             applied to the 2015 edition since Rust 1.36, so code or posts showing these \
+             borrows rejected as 2015 are describing an older compiler, not the edition"
+        );
+    }
+
+    Ok(())
+}
+
+fn snapshots(args: &[String]) -> Result<(), Error> {
+    let bless = args.iter().any(|arg| arg == "--bless");
+    let ids: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--bless")
+        .cloned()
+        .collect();
+    let lessons = lessons_or_all(&ids)?;
+
+    let failed = |e: io::Error| Error::Failed(e.to_string());
+    let exe = env::current_exe().map_err(failed)?;
+    let mut mismatched = 0;
+
+    for lesson in lessons {
+        let output = snapshot::capture(&exe, lesson).map_err(failed)?;
+
+        if bless {
+            snapshot::bless(lesson, &output).map_err(failed)?;
+            println!("{}: recorded", lesson.id);
+            continue;
+        }
+
+        match snapshot::compare(lesson, &output).map_err(failed)? {
+            Status::Matches => println!("{}: ok", lesson.id),
+            Status::Missing => {
+                println!(
+                    "{}: no recorded output, run with --bless to record it",
+                    lesson.id
+                );
+                mismatched += 1;
+            }
+            Status::Differs(diff) => {
+                println!(
+                    "{}: output changed (- recorded, + actual)\n{}",
+                    lesson.id, diff
+                );
+                mismatched += 1;
+            }
+        }
+    }
+
+    match mismatched {
+        0 => Ok(()),
+        n => Err(Error::Failed(format!(
+            "{} lesson(s) did not match their recorded output",
+            n
+        ))),
+    }
+}
+
+fn memory(args: &[String]) -> Result<(), Error> {
+    let (format, rest) = match args {
+        [flag, format, rest @ ..] if flag == "--format" => {
+            let format = memory::Format::parse(format).ok_or_else(|| {
+                Error::Usage(format!(
+                    "unknown format '{}', expected ascii, dot or svg",
+                    format
+                ))
+            })?;
+            (format, rest)
+        }
+        _ => (memory::Format::Ascii, args),
+    };
+
+    let lesson = single_lesson("memory", rest)?;
+    let diagrams = match lesson.memory {
+        Some(memory) => memory(),
+        None => {
+            return Err(Error::Failed(format!(
+                "'{}' has no memory diagrams",
+                lesson.id
+            )))
+        }
+    };
+
+    print!("{}", memory::render(&diagrams, format));
+    Ok(())
+}
+
+// Loads a bundled toy program by name, or a toy program from a file
+fn load_toy(name: &str) -> Result<Program, Error> {
+    let (file, source) = match toy::find(name) {
+        Some(source) => (format!("{}.toy", name), source.to_string()),
+        None => {
+            let source = fs::read_to_string(name).map_err(|e| {
+                Error::Failed(format!(
+                    "'{}' is not a bundled toy program and could not be read: {}",
+                    name, e
+                ))
+            })?;
+            (name.to_string(), source)
+        }
+    };
+
+    // A parse error is rendered like the checker's errors, which start
+    // with their own `error:`
+    Program::parse(&file, &source).map_err(|e| {
+        eprintln!("{}", e.render(&file, &source));
+        Error::Failed(String::from("aborting due to 1 previous error"))
+    })
+}
+
+// Loads a toy program and checks it, printing its errors if it has any.
+// `explain` prints what the checker concluded at each step as well
+fn checked_toy(name: &str, explain: bool) -> Result<Program, Error> {
+    let program = load_toy(name)?;
+    let report = program.check();
 
-    // References allow you to refer to some value
-    // without taking ownership of it
-    let s1 = String::from("Hello");
+    if explain {
+        for step in &report.steps {
+            println!("{}", step);
+        }
+        println!();
+    }
 
-    let len = calculate_length(&s1);
+    if !report.is_ok() {
+        for diagnostic in &report.diagnostics {
+            eprintln!("{}", program.render(diagnostic));
+        }
+        return Err(Error::Failed(format!(
+            "aborting due to {} previous error{}",
+            report.diagnostics.len(),
+            if report.diagnostics.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        )));
+    }
 
-    // We are allowed to use `s1` here because it was
-    // passed by reference above, the scope of `s1`
-    // did not change since the ownership did not change
-    println!("The length of '{}' is {}", s1, len);
+    Ok(program)
+}
+
+fn toy(args: &[String]) -> Result<(), Error> {
+    let explain = args.iter().any(|arg| arg == "--explain");
+    let names: Vec<&String> = args.iter().filter(|arg| *arg != "--explain").collect();
+
+    let name = match names[..] {
+        [] => {
+            for (name, _) in toy::PROGRAMS {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        [name] => name,
+        _ => return Err(Error::Usage(String::from("`toy` takes a single program"))),
+    };
+
+    let program = checked_toy(name, explain)?;
+    let output = program.run().map_err(Error::Failed)?;
+    print!("{}", output);
+
+    Ok(())
+}
+
+fn list_exercises() -> Result<(), Error> {
+    let exercises = exercise::all().map_err(|e| Error::Failed(e.to_string()))?;
+    let width = exercises
+        .iter()
+        .map(|e| e.example.id.len())
+        .max()
+        .unwrap_or(0);
+
+    for exercise in exercises {
+        println!(
+            "{:width$}  fix the {} in `{}`",
+            exercise.example.id,
+            exercise.example.code,
+            exercise.lesson.id,
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+fn find_exercise(id: &str) -> Result<exercise::Exercise, Error> {
+    exercise::find(id)
+        .map_err(|e| Error::Failed(e.to_string()))?
+        .ok_or_else(|| {
+            Error::Failed(format!(
+                "no exercise named '{}' (try `references exercise`)",
+                id
+            ))
+        })
+}
 
-    let mut s = String::from("Hello");
+fn write_exercise(id: &str, dir: &Path) -> Result<(), Error> {
+    let exercise = find_exercise(id)?;
+    let (path, created) = exercise
+        .write(dir)
+        .map_err(|e| Error::Failed(format!("{}: {}", dir.display(), e)))?;
 
-    // This is how pass by reference works by default in C
-    // In Rust however, `&mut` must be used to let the compiler
-    // know that the contents of the memory are able to be changed
-    // within the new scope we are passing `s` into
-    change(&mut s);
+    record_progress(|progress, now| {
+        progress.exercise_started(id, now);
+    });
 
-    println!("{}", s);
+    if created {
+        println!("Wrote {}", path.display());
+    } else {
+        println!("{} already exists, keeping your changes", path.display());
+    }
+    println!("Fix it, then run `references check {}`", path.display());
 
-    // The one big restriction of mutable references is that
-    // you can only have one mutable reference to a particular
-    // piece of data within a particular scope
-    // For example:
-    let mut s = String::from("Hello");
+    Ok(())
+}
 
-    let r1 = &mut s;
+// The code in an exercise file and the exercise it is for
+fn read_exercise(file: &Path) -> Result<(String, exercise::Exercise), Error> {
+    let code = fs::read_to_string(file)
+        .map_err(|e| Error::Failed(format!("{}: {}", file.display(), e)))?;
+    let id = exercise::id_of(file, &code).unwrap_or_default();
+    let exercise = find_exercise(&id)?;
 
-    // This is a compiler error "cannot borrow `s` as mutable more than
-    // once at a time"
-    // let r2 = &mut s;
+    Ok((code, exercise))
+}
 
-    println!("{}", r1);
+// The hint log kept in the directory an exercise file is in
+fn hint_log(file: &Path) -> Result<hints::Log, Error> {
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+    hints::Log::load(dir).map_err(|e| Error::Failed(format!("{}: {}", dir.display(), e)))
+}
 
-    // The benefit of having this restriction on mutable variables is that
-    // Rust can prevent race conditions at compile time
-    // Curly brackets can be used to create a new scope, however, which
-    // allows multiple mutable references, but not simultaneous ones
-    let mut s = String::from("Hello");
+fn save_hint_log(log: &hints::Log) -> Result<(), Error> {
+    log.save()
+        .map_err(|e| Error::Failed(format!("could not record hints: {}", e)))
+}
 
-    {
-        let r1 = &mut s;
-        println!("{}", r1);
-    } // r1 goes out of scope here and the `drop()` method is called
+// Grades `code` and, if `counts` is set, records the attempt in the
+// student's progress and a failure in the hint log, returning the checks
+// and the student's hint record
+fn grade(
+    file: &Path,
+    exercise: &exercise::Exercise,
+    code: &str,
+    counts: bool,
+) -> Result<(Vec<exercise::Check>, hints::Record), Error> {
+    let checks = exercise
+        .grade(code)
+        .map_err(|e| Error::Failed(format!("could not grade {}: {}", file.display(), e)))?;
 
-    // We can make a new reference without a problem now because there is no other
-    // reference to `s` within the current scope since it's memory was freed back to the heap
-    let r2 = &mut s;
-    println!("{}", r2);
+    let id = &exercise.example.id;
+    let passed = checks.iter().all(|check| check.passed);
+    if counts {
+        record_progress(|progress, now| progress.attempt(id, passed, now));
+    }
 
-    // A similar rule exists for combining mutable and immutable references
-    let mut s = String::from("Hello");
+    let mut log = hint_log(file)?;
+    let record = log.record(&hints::student(), id);
+    if !passed && counts {
+        record.failures += 1;
+    }
+    let record = record.clone();
+    save_hint_log(&log)?;
 
-    let r1 = &s;
-    let r2 = &s;
+    Ok((checks, record))
+}
 
-    // Line 68 is a compiler error because we are trying to borrow
-    // `s` as mutable when it was borrowed as immutable above by
-    // `r1` and `r2`, which could result in a race condition later on
-    // if `r3` modifies the value of `s`
-    // let r3 = &mut s;
+// The hint level that the failed check just made unlocked, if any
+fn new_hint(record: &hints::Record) -> Option<hints::Level> {
+    let unlocked = hints::unlocked(record.failures);
+    if record.failures > 0 && unlocked > hints::unlocked(record.failures - 1) {
+        Some(hints::LEVELS[unlocked - 1])
+    } else {
+        None
+    }
+}
+
+fn check(file: &Path) -> Result<(), Error> {
+    let (code, exercise) = read_exercise(file)?;
+    let (checks, record) = grade(file, &exercise, &code, true)?;
+
+    for check in &checks {
+        println!(
+            "{}  {}",
+            if check.passed { "ok  " } else { "FAIL" },
+            check.description
+        );
+        if !check.passed {
+            for line in check.details.trim_end().lines() {
+                println!("      {}", line);
+            }
+        }
+    }
 
-    println!("r1={} and r2={}", r1, r2);
+    let id = &exercise.example.id;
+    if checks.iter().all(|check| check.passed) {
+        println!(
+            "\n{} passed, using {} of {} hints",
+            id,
+            record.hints_used,
+            hints::LEVELS.len()
+        );
+        return Ok(());
+    }
 
-    // We can, however, create a mutable reference to `s` after
-    // we have already used (or, read from) `r1` and `r2`
-    // The scopes of `r1` and `r2` end after they have last been used (Line 70)
-    let r3 = &mut s;
-    println!("r3={}", r3);
+    if let Some(level) = new_hint(&record) {
+        println!(
+            "\nA {} hint is unlocked: `references hint {}`",
+            level.name(),
+            file.display()
+        );
+    }
+
+    Err(Error::Failed(format!("{} has not been solved yet", id)))
+}
 
-    // The Rust compiler also protects you against accidentally creating
-    // a dangling pointer, which is a pointer that references a location
-    // in memory that may have been given to someone else. If you have
-    // a reference to some data, the compiler will ensure that the data
-    // will not go out of scope before the reference to the data does
-    // let reference_to_nothing = dangle();
+fn watch(target: &str) -> Result<(), Error> {
+    let file = if Path::new(target).is_file() {
+        PathBuf::from(target)
+    } else {
+        let exercise = find_exercise(target)?;
+        let dir = Path::new(exercise::WORKSPACE);
+        let (path, _) = exercise
+            .write(dir)
+            .map_err(|e| Error::Failed(format!("{}: {}", dir.display(), e)))?;
+        path
+    };
 
-    let reference_to_something = no_dangle();
+    let mut watcher = Watcher::new(&file);
+    loop {
+        let code = watcher
+            .wait()
+            .map_err(|e| Error::Failed(format!("{}: {}", file.display(), e)))?;
+        let id = exercise::id_of(&file, &code).unwrap_or_default();
+        let exercise = find_exercise(&id)?;
+        // A save is often half-way through an edit, so only `check` counts
+        // as an attempt or towards unlocking hints
+        let (checks, record) = grade(&file, &exercise, &code, false)?;
 
-    println!("reference_to_something={}", reference_to_something);
+        // Clear the screen so that only the latest result is on it
+        print!("\x1b[2J\x1b[H");
+        println!("Watching {}, press Ctrl-C to stop\n", file.display());
+        print_status(&file, &exercise, &checks, &record)?;
+        io::stdout().flush().ok();
+    }
 }
 
-fn calculate_length(s: &String) -> usize {
-    s.len()
-} // `s` goes out of scope here, but because the function
-  // did not have ownership of it, it cannot call the `drop()`
-  // method on it
+// A short summary of a graded exercise: every check on one line, with the
+// compiler's errors tied to their lessons, and what to do next
+fn print_status(
+    file: &Path,
+    exercise: &exercise::Exercise,
+    checks: &[exercise::Check],
+    record: &hints::Record,
+) -> Result<(), Error> {
+    for check in checks {
+        println!(
+            "{}  {}",
+            if check.passed { "ok  " } else { "FAIL" },
+            check.description
+        );
+        if check.passed {
+            continue;
+        }
+
+        if !check.diagnostics.is_empty() {
+            for diagnostic in &check.diagnostics {
+                let line = diagnostic
+                    .spans
+                    .iter()
+                    .find(|span| span.primary)
+                    .map_or(0, |span| span.line);
+                let lesson = diagnostic
+                    .code
+                    .as_deref()
+                    .and_then(catalog::find)
+                    .map(|entry| format!(" (see `{}`)", entry.lesson))
+                    .unwrap_or_default();
+                println!(
+                    "      {} line {}: {}{}",
+                    diagnostic.code.as_deref().unwrap_or("error"),
+                    line,
+                    diagnostic.message,
+                    lesson
+                );
+            }
+        } else {
+            for line in check.details.trim_end().lines().take(6) {
+                println!("      {}", line);
+            }
+        }
+    }
+
+    let passed = checks.iter().filter(|check| check.passed).count();
+    println!();
+    if passed == checks.len() {
+        println!(
+            "{} passed, using {} of {} hints",
+            exercise.example.id,
+            record.hints_used,
+            hints::LEVELS.len()
+        );
+        println!(
+            "Record it in your progress with `references check {}`",
+            file.display()
+        );
+        return Ok(());
+    }
+
+    println!("{} of {} checks pass", passed, checks.len());
+    let unlocked = hints::unlocked(record.failures);
+    if unlocked > record.hints_used {
+        println!(
+            "A {} hint is waiting: `references hint {}`",
+            hints::LEVELS[unlocked - 1].name(),
+            file.display()
+        );
+    } else if let Some(next) = hints::LEVELS.get(unlocked) {
+        let needed = next.failures_needed() - record.failures;
+        println!(
+            "The {} hint unlocks after {} more failed check{}",
+            next.name(),
+            needed,
+            if needed == 1 { "" } else { "s" }
+        );
+    } else {
+        println!(
+            "Every hint has been shown: `references hint {}`",
+            file.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn hint(file: &Path) -> Result<(), Error> {
+    let (_, exercise) = read_exercise(file)?;
+    let id = &exercise.example.id;
+    let hints = hints::for_exercise(id).map_err(|e| Error::Failed(e.to_string()))?;
+    if hints.is_empty() {
+        return Err(Error::Failed(format!("there are no hints for {}", id)));
+    }
+
+    let mut log = hint_log(file)?;
+    let record = log.record(&hints::student(), id);
+    let unlocked = hints::unlocked(record.failures).min(hints.len());
+
+    for hint in &hints[..unlocked] {
+        println!("Hint ({}):\n{}", hint.level.name(), hint.text);
+    }
+
+    match hints.get(unlocked) {
+        Some(next) => {
+            let needed = next.level.failures_needed() - record.failures;
+            println!(
+                "The {} hint unlocks after {} more failed check{} of {}",
+                next.level.name(),
+                needed,
+                if needed == 1 { "" } else { "s" },
+                file.display()
+            );
+        }
+        None => println!("That is every hint for {}", id),
+    }
+
+    record.hints_used = record.hints_used.max(unlocked);
+    save_hint_log(&log)
+}
+
+fn quiz(ids: &[String]) -> Result<(), Error> {
+    let lessons = lessons_or_all(ids)?;
+    let stdin = io::stdin();
+    let mut input = stdin.lock().lines();
+    let (mut right, mut asked) = (0, 0);
+
+    'lessons: for lesson in lessons {
+        let questions = quiz::questions(lesson).map_err(|e| Error::Failed(e.to_string()))?;
+        quiz::prepare(&questions, &Cache::open())
+            .map_err(|e| Error::Failed(format!("could not run rustc: {}", e)))?;
+        let (mut lesson_right, mut lesson_asked) = (0, 0);
+
+        for (n, question) in questions.iter().enumerate() {
+            let prompt = question
+                .prompt()
+                .map_err(|e| Error::Failed(format!("could not run rustc: {}", e)))?;
+            println!(
+                "--- {} ({} of {}) ---\n\n{}\n\n{}",
+                lesson.id,
+                n + 1,
+                questions.len(),
+                question.snippet,
+                prompt
+            );
+
+            let given = match read_answer(&mut input, question.kind) {
+                Some(given) => given,
+                None => {
+                    record_quiz(lesson.id, lesson_right, lesson_asked);
+                    break 'lessons;
+                }
+            };
+            let verdict = question
+                .check(&given)
+                .map_err(|e| Error::Failed(format!("could not check the answer: {}", e)))?;
+
+            lesson_asked += 1;
+            if verdict.correct {
+                lesson_right += 1;
+                println!("Right: {}\n", verdict.answer);
+            } else {
+                println!("Not quite: {}\n", verdict.answer);
+            }
+        }
+
+        record_quiz(lesson.id, lesson_right, lesson_asked);
+        right += lesson_right;
+        asked += lesson_asked;
+    }
+
+    println!("Score: {} of {}", right, asked);
+    Ok(())
+}
+
+// One line, or for what a program prints every line up to an empty one.
+// Nothing means the input has ended
+fn read_answer(input: &mut impl Iterator<Item = io::Result<String>>, kind: Kind) -> Option<String> {
+    print!("> ");
+    io::stdout().flush().ok();
+
+    let first = input.next()?.ok()?;
+    if kind != Kind::Prints {
+        return Some(first);
+    }
 
-// This is a compiler error because the parameter some_string
-// is not explicitely labeled as mutable with `&mut`
-// fn change(some_string: &String) {
-//    some_string.push_str(", world");
-// }
+    let mut lines = vec![first];
+    while lines.last().is_some_and(|line| !line.trim().is_empty()) {
+        print!("> ");
+        io::stdout().flush().ok();
+        match input.next() {
+            Some(Ok(line)) => lines.push(line),
+            _ => break,
+        }
+    }
 
-// To pass by reference with the intention of modifying
-// the value, the passed in variable and parameter must be
-// mutable
-fn change(some_string: &mut String) {
-    some_string.push_str(", world");
+    Some(lines.join("\n"))
 }
 
-// fn dangle() -> &String {
-//     let s = String::from("Hello");
+fn solve_puzzle(seed: u64) -> Result<(), Error> {
+    let puzzle = puzzle::generate(seed);
+    println!(
+        "Puzzle {} (`references puzzle {}` shows it again)\n\n{}\nDoes this compile? (yes or no)",
+        puzzle.seed, puzzle.seed, puzzle.source
+    );
 
-     // The problem with this return on Line 111 is that `s` goes
-     // out of scope as soon as this function ends. Its memory goes away entirely,
-     // so we are returning a reference to nothing
-     // &s
-// }
+    let stdin = io::stdin();
+    let given = match read_answer(&mut stdin.lock().lines(), Kind::Compiles) {
+        Some(given) => given,
+        None => return Ok(()),
+    };
+    let verdict = puzzle
+        .verdict(&Cache::open())
+        .map_err(|e| Error::Failed(format!("could not run rustc: {}", e)))?;
+
+    let said_yes = matches!(given.trim().to_lowercase().as_str(), "y" | "yes");
+    println!(
+        "{}: `rustc` {}",
+        if said_yes == verdict.compiles {
+            "Right"
+        } else {
+            "Not quite"
+        },
+        if verdict.compiles {
+            "compiles it"
+        } else {
+            "rejects it"
+        }
+    );
+    let mut shown: Vec<&String> = Vec::new();
+    for code in &verdict.codes {
+        if shown.contains(&code) {
+            continue;
+        }
+        shown.push(code);
+
+        match catalog::find(code) {
+            Some(entry) => println!("    {}  {}", code, entry.headline()),
+            None => println!("    {}", code),
+        }
+    }
+
+    Ok(())
+}
+
+fn record_quiz(lesson: &str, right: usize, asked: usize) {
+    if asked > 0 {
+        record_progress(|progress, now| progress.quiz_taken(lesson, right, asked, now));
+    }
+}
+
+// Progress is recorded on the side, so failing to record it is only
+// warned about rather than failing the command
+fn record_progress(update: impl FnOnce(&mut Progress, u64)) {
+    if !progress::enabled() {
+        return;
+    }
+    let path = match progress::path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let result = Progress::load(&path).and_then(|mut progress| {
+        update(&mut progress, progress::now());
+        progress.save()
+    });
+    if let Err(e) = result {
+        eprintln!("warning: could not record progress: {}", e);
+    }
+}
+
+fn progress_path() -> Result<PathBuf, Error> {
+    progress::path().ok_or_else(|| {
+        Error::Failed(format!(
+            "no data directory to keep progress in (set {})",
+            progress::DIR_VARIABLE
+        ))
+    })
+}
+
+fn show_progress() -> Result<(), Error> {
+    let path = progress_path()?;
+    let progress = Progress::load(&path).map_err(|e| Error::Failed(e.to_string()))?;
+    let exercises = exercise::all().map_err(|e| Error::Failed(e.to_string()))?;
+    let now = progress::now();
+    let width = lesson::all()
+        .iter()
+        .map(|lesson| lesson.id)
+        .chain(exercises.iter().map(|e| e.example.id.as_str()))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let mark = |done: bool| if done { "[x]" } else { "[ ]" };
+
+    println!("Progress recorded in {}\n", path.display());
+
+    let run = lesson::all()
+        .iter()
+        .filter(|lesson| progress.lesson(lesson.id).is_some())
+        .count();
+    println!("Lessons: {} of {} run", run, lesson::all().len());
+    for lesson in lesson::all() {
+        let record = progress.lesson(lesson.id);
+        let detail = match record {
+            Some(record) => format!(
+                "run {} time{}, last {} ago",
+                record.runs,
+                if record.runs == 1 { "" } else { "s" },
+                progress::duration(now.saturating_sub(record.last_run))
+            ),
+            None => String::new(),
+        };
+        let line = format!(
+            "  {} {:width$}  {}",
+            mark(record.is_some()),
+            lesson.id,
+            detail,
+            width = width
+        );
+        println!("{}", line.trim_end());
+    }
+
+    let passed = exercises
+        .iter()
+        .filter(|e| progress.passed(&e.example.id))
+        .count();
+    println!("\nExercises: {} of {} passed", passed, exercises.len());
+    for exercise in &exercises {
+        let record = progress.exercise(&exercise.example.id);
+        let attempts = |n: usize| format!("{} attempt{}", n, if n == 1 { "" } else { "s" });
+        let detail = match record {
+            Some(record) if record.passed.is_some() => format!(
+                "passed after {}, {} after starting",
+                attempts(record.attempts),
+                progress::duration(record.elapsed(now))
+            ),
+            Some(record) => format!(
+                "{} so far, started {} ago",
+                attempts(record.attempts),
+                progress::duration(record.elapsed(now))
+            ),
+            None => String::from("not started"),
+        };
+        println!(
+            "  {} {:width$}  {}",
+            mark(progress.passed(&exercise.example.id)),
+            exercise.example.id,
+            detail,
+            width = width
+        );
+    }
+
+    if !progress.quizzes.is_empty() {
+        println!("\nQuizzes:");
+        for lesson in lesson::all() {
+            if let Some(quiz) = progress.quiz(lesson.id) {
+                println!(
+                    "      {:width$}  last {} of {}, best {} of {}, taken {} time{}",
+                    lesson.id,
+                    quiz.last.0,
+                    quiz.last.1,
+                    quiz.best.0,
+                    quiz.best.1,
+                    quiz.taken,
+                    if quiz.taken == 1 { "" } else { "s" },
+                    width = width
+                );
+            }
+        }
+    }
+
+    let next_lesson = lesson::all()
+        .iter()
+        .find(|lesson| progress.lesson(lesson.id).is_none());
+    let next_exercise = exercises.iter().find(|e| !progress.passed(&e.example.id));
+    match (next_lesson, next_exercise) {
+        (Some(lesson), _) => println!("\nNext: `references run {}`", lesson.id),
+        (None, Some(exercise)) => {
+            println!("\nNext: `references exercise {}`", exercise.example.id)
+        }
+        (None, None) => println!("\nEvery lesson has been run and every exercise passed"),
+    }
+
+    Ok(())
+}
+
+fn reset() -> Result<(), Error> {
+    let path = progress_path()?;
+    let removed =
+        progress::reset(&path).map_err(|e| Error::Failed(format!("{}: {}", path.display(), e)))?;
+
+    if removed {
+        println!("Forgot the progress recorded in {}", path.display());
+    } else {
+        println!("No progress has been recorded yet");
+    }
+
+    Ok(())
+}
+
+fn list_errors(entries: Vec<&catalog::Entry>) -> Result<(), Error> {
+    for entry in entries {
+        println!("{}  {}", entry.code, entry.headline());
+    }
+
+    Ok(())
+}
+
+fn errors(query: &str) -> Result<(), Error> {
+    let found =
+        catalog::search(query).map_err(|e| Error::Failed(format!("could not run rustc: {}", e)))?;
+
+    let entry = match found[..] {
+        [] => {
+            return Err(Error::Failed(format!(
+                "no borrowing error mentions '{}'",
+                query
+            )))
+        }
+        [entry] => entry,
+        _ => return list_errors(found),
+    };
+
+    println!("{}\n\n{}\n", entry.code, entry.summary);
+    print!("{}", entry.pointers());
+
+    let explanation = entry
+        .explanation()
+        .map_err(|e| Error::Failed(format!("could not run rustc: {}", e)))?;
+    println!(
+        "\nrustc --explain {}\n\n{}",
+        entry.code,
+        explanation.trim_end()
+    );
+
+    Ok(())
+}
+
+fn diagnose(file: &Path) -> Result<(), Error> {
+    let diagnostics =
+        diagnose::check(file).map_err(|e| Error::Failed(format!("{}: {}", file.display(), e)))?;
+
+    if diagnostics.is_empty() {
+        println!("{} compiles without errors", file.display());
+        return Ok(());
+    }
+
+    let explained: Vec<String> = diagnostics.iter().map(diagnose::explain).collect();
+    println!("{}", explained.join("\n"));
+
+    Err(Error::Failed(format!(
+        "{} error{} in {}",
+        diagnostics.len(),
+        if diagnostics.len() == 1 { "" } else { "s" },
+        file.display()
+    )))
+}
+
+fn fixtures(dir: &Path) -> Result<(), Error> {
+    let examples = examples::all().map_err(|e| Error::Failed(e.to_string()))?;
+    let failed = |e: io::Error| Error::Failed(format!("{}: {}", dir.display(), e));
+
+    fs::create_dir_all(dir).map_err(failed)?;
+
+    for example in examples {
+        let path = dir.join(format!("{}.rs", example.id));
+        fs::write(&path, &example.source).map_err(failed)?;
+        println!("{} ({})", path.display(), example.code);
+    }
+
+    Ok(())
+}
 
-// To avoid the dangling pointer problem, the `String` needs to be returned directly,
-// which ensures that "not nothing" will be returned to the caller
-fn no_dangle() -> String {
-    let s = String::from("Hello");
+// "line 45" or "lines 98, 126"
+fn line_list(lines: &[usize]) -> String {
+    let numbers: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
 
-    // Ownership is moved back to the caller and nothing is deallocated
-    s
+    match lines.len() {
+        1 => format!("line {}", numbers[0]),
+        _ => format!("lines {}", numbers.join(", ")),
+    }
 }
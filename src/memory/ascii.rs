@@ -0,0 +1,82 @@
+// Project: references
+// Author: Greg Folker
+
+// Diagrams drawn as plain text, with the stack on top and the heap below
+
+use super::{hex, Diagram, SlotValue};
+
+pub fn render(diagram: &Diagram) -> String {
+    let mut out = format!("{}\n\nstack\n", diagram.caption);
+
+    let name_width = diagram
+        .frames
+        .iter()
+        .flat_map(|frame| &frame.slots)
+        .map(|slot| slot.name.len())
+        .max()
+        .unwrap_or(0);
+
+    for frame in &diagram.frames {
+        out.push_str(&format!("  {}\n", frame.function));
+        if frame.slots.is_empty() {
+            out.push_str("    (no variables yet)\n");
+        }
+
+        for slot in &frame.slots {
+            let prefix = format!(
+                "    {}  {:width$}  ",
+                hex(slot.address),
+                slot.name,
+                width = name_width
+            );
+            let indent = " ".repeat(prefix.len());
+
+            match &slot.value {
+                SlotValue::String { ptr, len, capacity } => {
+                    let target = match diagram.buffer_at(*ptr) {
+                        Some(i) => format!("  --> heap #{}", i + 1),
+                        None => String::new(),
+                    };
+                    out.push_str(&format!(
+                        "{}String       ptr       {}{}\n",
+                        prefix,
+                        hex(*ptr),
+                        target
+                    ));
+                    out.push_str(&format!("{}             len       {}\n", indent, len));
+                    out.push_str(&format!("{}             capacity  {}\n", indent, capacity));
+                }
+                SlotValue::Ref { target, mutable } => {
+                    let ty = if *mutable { "&mut String" } else { "&String" };
+                    let name = match diagram.slot_at(*target) {
+                        Some(owner) => format!("  --> {}", owner.name),
+                        None => String::new(),
+                    };
+                    out.push_str(&format!("{}{:13}{}{}\n", prefix, ty, hex(*target), name));
+                }
+            }
+        }
+    }
+
+    out.push_str("\nheap\n");
+    for (i, buffer) in diagram.heap.iter().enumerate() {
+        let unused = buffer.capacity - buffer.len;
+        out.push_str(&format!(
+            "  #{}  {}  {:?}{}\n",
+            i + 1,
+            hex(buffer.address),
+            buffer.text,
+            if unused > 0 {
+                format!(
+                    " and {} unused byte{}",
+                    unused,
+                    if unused == 1 { "" } else { "s" }
+                )
+            } else {
+                String::new()
+            }
+        ));
+    }
+
+    out
+}
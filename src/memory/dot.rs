@@ -0,0 +1,113 @@
+// Project: references
+// Author: Greg Folker
+
+// Diagrams as Graphviz DOT, one `digraph` for each, to be drawn with
+// `dot -Tpng` or any other Graphviz tool
+
+use super::{hex, Diagram, SlotValue};
+
+pub fn render(diagram: &Diagram) -> String {
+    let mut out = String::from("digraph memory {\n");
+    out.push_str(&format!(
+        "    label=\"{}\";\n    labelloc=t;\n    rankdir=LR;\n",
+        escape(&diagram.caption)
+    ));
+    out.push_str("    node [shape=record, fontname=\"monospace\"];\n\n");
+
+    // Every variable gets a node named after its address, so references
+    // can point at the variable they hold the address of
+    out.push_str("    subgraph cluster_stack {\n        label=\"stack\";\n");
+    for (f, frame) in diagram.frames.iter().enumerate() {
+        out.push_str(&format!(
+            "        subgraph cluster_frame{} {{\n            label=\"{}\";\n",
+            f,
+            escape(&frame.function)
+        ));
+        if frame.slots.is_empty() {
+            out.push_str(&format!(
+                "            empty{} [shape=plaintext, label=\"(no variables yet)\"];\n",
+                f
+            ));
+        }
+
+        for slot in &frame.slots {
+            // Only addresses and numbers, which need no escaping
+            let fields = match &slot.value {
+                SlotValue::String { ptr, len, capacity } => format!(
+                    "{{<ptr> ptr: {} | len: {} | capacity: {}}}",
+                    hex(*ptr),
+                    len,
+                    capacity
+                ),
+                SlotValue::Ref { target, mutable } => format!(
+                    "{}: {}",
+                    if *mutable { "&mut String" } else { "&String" },
+                    hex(*target)
+                ),
+            };
+            out.push_str(&format!(
+                "            slot{} [label=\"{{{} | {}}} | {}\"];\n",
+                hex(slot.address),
+                record(&slot.name),
+                hex(slot.address),
+                fields
+            ));
+        }
+        out.push_str("        }\n");
+    }
+    out.push_str("    }\n\n");
+
+    out.push_str("    subgraph cluster_heap {\n        label=\"heap\";\n");
+    for buffer in &diagram.heap {
+        let unused = buffer.capacity - buffer.len;
+        out.push_str(&format!(
+            "        heap{} [label=\"{} | {} | {} unused\"];\n",
+            hex(buffer.address),
+            hex(buffer.address),
+            record(&format!("{:?}", buffer.text)),
+            unused
+        ));
+    }
+    out.push_str("    }\n\n");
+
+    for slot in diagram.frames.iter().flat_map(|frame| &frame.slots) {
+        match &slot.value {
+            SlotValue::String { ptr, .. } if diagram.buffer_at(*ptr).is_some() => {
+                out.push_str(&format!(
+                    "    slot{}:ptr -> heap{};\n",
+                    hex(slot.address),
+                    hex(*ptr)
+                ));
+            }
+            SlotValue::Ref { target, .. } if diagram.slot_at(*target).is_some() => {
+                out.push_str(&format!(
+                    "    slot{} -> slot{};\n",
+                    hex(slot.address),
+                    hex(*target)
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+// Text inside a double-quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Text inside a record label, where the characters that lay out fields
+// have to be escaped as well
+fn record(text: &str) -> String {
+    let mut out = String::new();
+    for c in escape(text).chars() {
+        if "{}|<>".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,154 @@
+// Project: references
+// Author: Greg Folker
+
+// The lessons from `references.rs` again, taking a diagram of the stack
+// and heap at the moments worth looking at instead of printing. Each one
+// follows the lesson of the same name
+
+// Borrows are of the `String` itself, as in `references.rs`
+#![allow(clippy::ptr_arg)]
+
+use super::{Buffer, Diagram, Frame};
+
+pub fn borrowing() -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let s1 = String::from("Hello");
+
+    let main = Frame::new("main").string("s1", &s1);
+    let _len = calculate_length(&s1, main, &mut diagrams);
+
+    diagrams
+}
+
+pub fn mutable_references() -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let mut s = String::from("Hello");
+
+    change(&mut s, &mut diagrams);
+
+    diagrams
+}
+
+pub fn mutable_aliasing() -> Vec<Diagram> {
+    let mut s = String::from("Hello");
+
+    let r1 = &mut s;
+    let buffer = Buffer::of(r1);
+    let main = Frame::new("main").string("s", r1).reference_mut("r1", &r1);
+
+    vec![Diagram::new(
+        "after `let r1 = &mut s`",
+        vec![main],
+        vec![buffer],
+    )]
+}
+
+pub fn scoped_borrows() -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let mut s = String::from("Hello");
+
+    {
+        let r1 = &mut s;
+        let buffer = Buffer::of(r1);
+        let main = Frame::new("main").string("s", r1).reference_mut("r1", &r1);
+        diagrams.push(Diagram::new("inside the block", vec![main], vec![buffer]));
+    }
+
+    let r2 = &mut s;
+    let buffer = Buffer::of(r2);
+    let main = Frame::new("main").string("s", r2).reference_mut("r2", &r2);
+    diagrams.push(Diagram::new(
+        "after the block, `r1` is gone and `r2` borrows `s`",
+        vec![main],
+        vec![buffer],
+    ));
+
+    diagrams
+}
+
+pub fn shared_and_mutable() -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let mut s = String::from("Hello");
+
+    let r1 = &s;
+    let r2 = &s;
+    let main = Frame::new("main")
+        .string("s", &s)
+        .reference("r1", &r1)
+        .reference("r2", &r2);
+    diagrams.push(Diagram::new(
+        "after `let r2 = &s`",
+        vec![main],
+        vec![Buffer::of(&s)],
+    ));
+
+    let r3 = &mut s;
+    let buffer = Buffer::of(r3);
+    let main = Frame::new("main").string("s", r3).reference_mut("r3", &r3);
+    diagrams.push(Diagram::new(
+        "after `let r3 = &mut s`, once `r1` and `r2` are no longer used",
+        vec![main],
+        vec![buffer],
+    ));
+
+    diagrams
+}
+
+pub fn dangling_references() -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+
+    let reference_to_something = no_dangle(&mut diagrams);
+
+    let main = Frame::new("main").string("reference_to_something", &reference_to_something);
+    diagrams.push(Diagram::new(
+        "after `no_dangle` returns, the `String` has moved but its heap buffer has not",
+        vec![main],
+        vec![Buffer::of(&reference_to_something)],
+    ));
+
+    diagrams
+}
+
+fn calculate_length(s: &String, main: Frame, diagrams: &mut Vec<Diagram>) -> usize {
+    let frame = Frame::new("calculate_length").reference("s", &s);
+    diagrams.push(Diagram::new(
+        "inside `calculate_length(&s1)`",
+        vec![main, frame],
+        vec![Buffer::of(s)],
+    ));
+
+    s.len()
+}
+
+fn change(some_string: &mut String, diagrams: &mut Vec<Diagram>) {
+    // The caller's `s` is read through the reference, which is the only
+    // way to reach it while it is mutably borrowed
+    let mut picture = |caption: &str, some_string: &&mut String| {
+        let main = Frame::new("main").string("s", some_string);
+        let frame = Frame::new("change").reference_mut("some_string", some_string);
+        diagrams.push(Diagram::new(
+            caption,
+            vec![main, frame],
+            vec![Buffer::of(some_string)],
+        ));
+    };
+
+    picture("inside `change(&mut s)`, before `push_str`", &some_string);
+    some_string.push_str(", world");
+    picture(
+        "after `push_str`, which may have moved the text to a bigger buffer",
+        &some_string,
+    );
+}
+
+fn no_dangle(diagrams: &mut Vec<Diagram>) -> String {
+    let s = String::from("Hello");
+
+    diagrams.push(Diagram::new(
+        "inside `no_dangle`, before it returns `s`",
+        vec![Frame::new("main"), Frame::new("no_dangle").string("s", &s)],
+        vec![Buffer::of(&s)],
+    ));
+
+    s
+}
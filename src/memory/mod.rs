@@ -0,0 +1,190 @@
+// Project: references
+// Author: Greg Folker
+
+// Pictures of where the lesson values live in memory. A `String` is a
+// pointer, a length and a capacity on the stack, with its text in a
+// buffer on the heap. A reference to it is only the address of those
+// three words
+//
+// Every address and length is read from the values while a lesson runs,
+// so they change from run to run but always show the real layout
+
+mod ascii;
+mod dot;
+pub mod lessons;
+mod svg;
+
+/// The stack and heap at one moment in a lesson
+#[derive(Clone)]
+pub struct Diagram {
+    /// When the picture was taken, such as "inside `change`"
+    pub caption: String,
+    /// The caller first
+    pub frames: Vec<Frame>,
+    pub heap: Vec<Buffer>,
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    pub function: String,
+    pub slots: Vec<Slot>,
+}
+
+/// A variable on the stack
+#[derive(Clone)]
+pub struct Slot {
+    pub name: String,
+    pub address: usize,
+    pub value: SlotValue,
+}
+
+#[derive(Clone)]
+pub enum SlotValue {
+    String {
+        ptr: usize,
+        len: usize,
+        capacity: usize,
+    },
+    /// A `&String` or `&mut String`, holding the address of the `String`
+    Ref { target: usize, mutable: bool },
+}
+
+/// The heap buffer a `String` points at
+#[derive(Clone)]
+pub struct Buffer {
+    pub address: usize,
+    pub len: usize,
+    pub capacity: usize,
+    pub text: String,
+}
+
+/// The formats a diagram can be drawn in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Ascii,
+    Dot,
+    Svg,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "ascii" => Some(Format::Ascii),
+            "dot" => Some(Format::Dot),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+impl Diagram {
+    /// A diagram of `frames` and the heap buffers of their `String`s.
+    /// The same buffer may be given more than once
+    pub fn new(caption: &str, frames: Vec<Frame>, heap: Vec<Buffer>) -> Diagram {
+        let mut buffers: Vec<Buffer> = Vec::new();
+        for buffer in heap {
+            if !buffers.iter().any(|b| b.address == buffer.address) {
+                buffers.push(buffer);
+            }
+        }
+
+        Diagram {
+            caption: caption.to_string(),
+            frames,
+            heap: buffers,
+        }
+    }
+
+    /// The variable stored at `address`, if it is in the diagram
+    pub fn slot_at(&self, address: usize) -> Option<&Slot> {
+        self.frames
+            .iter()
+            .flat_map(|frame| &frame.slots)
+            .find(|slot| slot.address == address)
+    }
+
+    /// The position of the heap buffer at `address`
+    pub fn buffer_at(&self, address: usize) -> Option<usize> {
+        self.heap.iter().position(|b| b.address == address)
+    }
+}
+
+impl Frame {
+    pub fn new(function: &str) -> Frame {
+        Frame {
+            function: function.to_string(),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Adds the `String` `value`, which must be the variable itself
+    /// rather than a copy, so that its address is the variable's
+    pub fn string(mut self, name: &str, value: &String) -> Frame {
+        self.slots.push(Slot {
+            name: name.to_string(),
+            address: value as *const String as usize,
+            value: SlotValue::String {
+                ptr: value.as_ptr() as usize,
+                len: value.len(),
+                capacity: value.capacity(),
+            },
+        });
+        self
+    }
+
+    /// Adds the reference variable `reference`, given by its own address
+    pub fn reference(mut self, name: &str, reference: &&String) -> Frame {
+        self.slots.push(Slot {
+            name: name.to_string(),
+            address: reference as *const &String as usize,
+            value: SlotValue::Ref {
+                target: *reference as *const String as usize,
+                mutable: false,
+            },
+        });
+        self
+    }
+
+    /// Adds the mutable reference variable `reference`
+    pub fn reference_mut(mut self, name: &str, reference: &&mut String) -> Frame {
+        self.slots.push(Slot {
+            name: name.to_string(),
+            address: reference as *const &mut String as usize,
+            value: SlotValue::Ref {
+                target: &**reference as *const String as usize,
+                mutable: true,
+            },
+        });
+        self
+    }
+}
+
+impl Buffer {
+    /// The buffer `value` points at, including its unused capacity
+    pub fn of(value: &String) -> Buffer {
+        Buffer {
+            address: value.as_ptr() as usize,
+            len: value.len(),
+            capacity: value.capacity(),
+            text: value.clone(),
+        }
+    }
+}
+
+/// Draws every diagram in `format`, one after another
+pub fn render(diagrams: &[Diagram], format: Format) -> String {
+    match format {
+        Format::Ascii => diagrams
+            .iter()
+            .map(ascii::render)
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Dot => diagrams.iter().map(dot::render).collect(),
+        Format::Svg => svg::render(diagrams),
+    }
+}
+
+// `0x7ffd5c3a1f20`
+fn hex(address: usize) -> String {
+    format!("{:#x}", address)
+}
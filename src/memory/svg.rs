@@ -0,0 +1,203 @@
+// Project: references
+// Author: Greg Folker
+
+// Diagrams as a single SVG image, one below the other. Each has the stack
+// on the left and the heap on the right, with arrows for every pointer
+
+use super::{hex, Diagram, SlotValue};
+
+const ROW: i64 = 22;
+const STACK_X: i64 = 60;
+const STACK_WIDTH: i64 = 420;
+const HEAP_X: i64 = 560;
+const HEAP_WIDTH: i64 = 320;
+const WIDTH: i64 = HEAP_X + HEAP_WIDTH + 20;
+
+pub fn render(diagrams: &[Diagram]) -> String {
+    let mut body = String::new();
+    let mut y = 10;
+
+    for diagram in diagrams {
+        y = draw(diagram, y, &mut body) + 30;
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"13\">\n\
+         <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+         markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\
+         <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{body}</svg>\n",
+        w = WIDTH,
+        h = y,
+        body = body
+    )
+}
+
+// Draws one diagram starting at `top` and returns where it ends
+fn draw(diagram: &Diagram, top: i64, out: &mut String) -> i64 {
+    out.push_str(&text(10, top + 16, &diagram.caption, Style::Bold));
+    out.push_str(&text(STACK_X, top + 44, "stack", Style::Bold));
+    out.push_str(&text(HEAP_X, top + 44, "heap", Style::Bold));
+
+    // Where each variable and heap buffer was drawn, for the arrows
+    let mut slots: Vec<(usize, i64)> = Vec::new();
+    let mut pointers: Vec<(i64, usize)> = Vec::new();
+    let mut references: Vec<(i64, usize)> = Vec::new();
+    let mut buffers: Vec<(usize, i64)> = Vec::new();
+
+    let mut y = top + 56;
+    for frame in &diagram.frames {
+        let rows = frame
+            .slots
+            .iter()
+            .map(|slot| match slot.value {
+                SlotValue::String { .. } => 3,
+                SlotValue::Ref { .. } => 2,
+            })
+            .sum::<i64>()
+            .max(1);
+
+        out.push_str(&rect(STACK_X, y, STACK_WIDTH, ROW * (rows + 1), "#eef3fb"));
+        out.push_str(&text(STACK_X + 8, y + 16, &frame.function, Style::Bold));
+        y += ROW;
+
+        if frame.slots.is_empty() {
+            out.push_str(&text(
+                STACK_X + 8,
+                y + 16,
+                "(no variables yet)",
+                Style::Normal,
+            ));
+            y += ROW;
+        }
+
+        for slot in &frame.slots {
+            let fields: Vec<String> = match &slot.value {
+                SlotValue::String { ptr, len, capacity } => {
+                    pointers.push((y + ROW / 2, *ptr));
+                    vec![
+                        format!("ptr      {}", hex(*ptr)),
+                        format!("len      {}", len),
+                        format!("capacity {}", capacity),
+                    ]
+                }
+                SlotValue::Ref { target, mutable } => {
+                    references.push((y + ROW / 2, *target));
+                    vec![format!(
+                        "{} {}",
+                        if *mutable { "&mut" } else { "&" },
+                        hex(*target)
+                    )]
+                }
+            };
+            // Room for the name and address, whatever the value takes up
+            let height = ROW * fields.len().max(2) as i64;
+
+            slots.push((slot.address, y + ROW / 2));
+            out.push_str(&rect(STACK_X + 4, y, STACK_WIDTH - 8, height, "white"));
+            out.push_str(&text(STACK_X + 12, y + 16, &slot.name, Style::Bold));
+            out.push_str(&text(
+                STACK_X + 12,
+                y + 16 + ROW,
+                &hex(slot.address),
+                Style::Grey,
+            ));
+            for (i, field) in fields.iter().enumerate() {
+                out.push_str(&text(
+                    STACK_X + 190,
+                    y + 16 + ROW * i as i64,
+                    field,
+                    Style::Normal,
+                ));
+            }
+            y += height;
+        }
+        y += 8;
+    }
+    let stack_end = y;
+
+    let mut y = top + 56;
+    for buffer in &diagram.heap {
+        let unused = buffer.capacity - buffer.len;
+        buffers.push((buffer.address, y + ROW));
+
+        out.push_str(&rect(HEAP_X, y, HEAP_WIDTH, ROW * 2, "#fbf3e6"));
+        out.push_str(&text(HEAP_X + 8, y + 16, &hex(buffer.address), Style::Grey));
+        out.push_str(&text(
+            HEAP_X + 8,
+            y + 16 + ROW,
+            &format!("{:?} + {} unused", buffer.text, unused),
+            Style::Normal,
+        ));
+        y += ROW * 2 + 12;
+    }
+    let heap_end = y;
+
+    for (from, address) in pointers {
+        if let Some((_, to)) = buffers.iter().find(|(a, _)| *a == address) {
+            out.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" \
+                 marker-end=\"url(#arrow)\"/>\n",
+                STACK_X + STACK_WIDTH - 4,
+                from,
+                HEAP_X,
+                to
+            ));
+        }
+    }
+
+    // References point at other variables on the stack, so they curve
+    // around the left of it
+    for (from, address) in references {
+        if let Some((_, to)) = slots.iter().find(|(a, _)| *a == address) {
+            out.push_str(&format!(
+                "<path d=\"M {x} {from} C {bend} {from}, {bend} {to}, {x} {to}\" \
+                 fill=\"none\" stroke=\"#3366cc\" marker-end=\"url(#arrow)\"/>\n",
+                x = STACK_X + 4,
+                bend = STACK_X - 50,
+                from = from,
+                to = to
+            ));
+        }
+    }
+
+    stack_end.max(heap_end)
+}
+
+fn rect(x: i64, y: i64, width: i64, height: i64, fill: &str) -> String {
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"black\"/>\n",
+        x, y, width, height, fill
+    )
+}
+
+enum Style {
+    Normal,
+    Bold,
+    /// For addresses, which are there to be matched up rather than read
+    Grey,
+}
+
+fn text(x: i64, y: i64, content: &str, style: Style) -> String {
+    let attributes = match style {
+        Style::Normal => "",
+        Style::Bold => " font-weight=\"bold\"",
+        Style::Grey => " fill=\"#666666\"",
+    };
+
+    format!(
+        "<text x=\"{}\" y=\"{}\"{}>{}</text>\n",
+        x,
+        y,
+        attributes,
+        escape(content)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,344 @@
+// Project: references
+// Author: Greg Folker
+
+// What a student has done so far: the lessons they have run, the
+// exercises they have attempted and passed, and their quiz scores. It is
+// kept in a small versioned text file in the user's data directory so
+// that working through the lessons can be picked up again in a later
+// sitting
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the progress file this build writes, and the newest it
+/// can read
+pub const VERSION: u32 = 2;
+
+// The first line of a progress file, followed by its version
+const HEADER: &str = "references progress ";
+
+/// Overrides where the progress file is kept
+pub const DIR_VARIABLE: &str = "REFERENCES_DATA_DIR";
+
+/// Set to stop anything being recorded, such as when lessons are run to
+/// compare their output rather than by a student
+pub const DISABLE_VARIABLE: &str = "REFERENCES_NO_PROGRESS";
+
+pub struct LessonRecord {
+    pub id: String,
+    pub runs: usize,
+    /// Seconds since the Unix epoch
+    pub last_run: u64,
+}
+
+pub struct ExerciseRecord {
+    pub id: String,
+    /// Times the exercise has been checked
+    pub attempts: usize,
+    /// When it was written out or first checked
+    pub started: u64,
+    /// When it was first checked and passed
+    pub passed: Option<u64>,
+}
+
+impl ExerciseRecord {
+    /// Seconds from starting the exercise to passing it, or until `now`
+    /// if it has not been passed yet. This is time on the clock, not time
+    /// spent working on it: an exercise started on Monday and passed on
+    /// Friday took four days
+    pub fn elapsed(&self, now: u64) -> u64 {
+        self.passed.unwrap_or(now).saturating_sub(self.started)
+    }
+}
+
+/// The scores of the quizzes on one lesson, added in version 2
+pub struct QuizRecord {
+    pub lesson: String,
+    pub taken: usize,
+    /// Right answers out of questions asked, the last time and at best
+    pub last: (usize, usize),
+    pub best: (usize, usize),
+    pub last_taken: u64,
+}
+
+pub struct Progress {
+    path: PathBuf,
+    pub lessons: Vec<LessonRecord>,
+    pub exercises: Vec<ExerciseRecord>,
+    pub quizzes: Vec<QuizRecord>,
+}
+
+/// Whether progress should be recorded at all
+pub fn enabled() -> bool {
+    env::var_os(DISABLE_VARIABLE).is_none()
+}
+
+/// The directory the progress file goes in: `REFERENCES_DATA_DIR` if it
+/// is set, or else the platform's per-user data directory
+pub fn data_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+
+    if let Some(dir) = var(DIR_VARIABLE) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let base = if cfg!(windows) {
+        var("APPDATA").map(PathBuf::from)?
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(var("HOME")?).join("Library/Application Support")
+    } else {
+        match var("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(var("HOME")?).join(".local/share"),
+        }
+    };
+
+    Some(base.join("references"))
+}
+
+/// Where the progress file is kept
+pub fn path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("progress"))
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn invalid(path: &Path, line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}:{}: {}", path.display(), line, message),
+    )
+}
+
+impl Progress {
+    /// The progress recorded at `path`, or none yet if the file does not
+    /// exist
+    pub fn load(path: &Path) -> io::Result<Progress> {
+        let mut progress = Progress {
+            path: path.to_path_buf(),
+            lessons: Vec::new(),
+            exercises: Vec::new(),
+            quizzes: Vec::new(),
+        };
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(progress),
+            Err(e) => return Err(e),
+        };
+        let mut lines = text.lines();
+
+        let version = lines
+            .next()
+            .and_then(|line| line.strip_prefix(HEADER))
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| invalid(path, 1, "not a progress file"))?;
+        if version > VERSION {
+            return Err(invalid(
+                path,
+                1,
+                &format!(
+                    "written by a newer version (format {}, this one reads up to {})",
+                    version, VERSION
+                ),
+            ));
+        }
+
+        for (n, line) in lines.enumerate() {
+            let malformed = || invalid(path, n + 2, "malformed record");
+            let number = |field: &str| field.parse::<u64>().map_err(|_| malformed());
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            match fields[..] {
+                ["lesson", id, runs, last_run] => progress.lessons.push(LessonRecord {
+                    id: id.to_string(),
+                    runs: number(runs)? as usize,
+                    last_run: number(last_run)?,
+                }),
+                ["exercise", id, attempts, started, passed] => {
+                    progress.exercises.push(ExerciseRecord {
+                        id: id.to_string(),
+                        attempts: number(attempts)? as usize,
+                        started: number(started)?,
+                        passed: match passed {
+                            "-" => None,
+                            passed => Some(number(passed)?),
+                        },
+                    })
+                }
+                ["quiz", lesson, taken, last, last_of, best, best_of, last_taken] => {
+                    progress.quizzes.push(QuizRecord {
+                        lesson: lesson.to_string(),
+                        taken: number(taken)? as usize,
+                        last: (number(last)? as usize, number(last_of)? as usize),
+                        best: (number(best)? as usize, number(best_of)? as usize),
+                        last_taken: number(last_taken)?,
+                    })
+                }
+                [""] => {}
+                _ => return Err(malformed()),
+            }
+        }
+
+        Ok(progress)
+    }
+
+    /// Writes the progress out, replacing the file in one step so that
+    /// it is never left half written
+    pub fn save(&self) -> io::Result<()> {
+        let mut text = format!("{}{}\n", HEADER, VERSION);
+        for lesson in &self.lessons {
+            text.push_str(&format!(
+                "lesson\t{}\t{}\t{}\n",
+                lesson.id, lesson.runs, lesson.last_run
+            ));
+        }
+        for exercise in &self.exercises {
+            let passed = exercise
+                .passed
+                .map_or_else(|| String::from("-"), |passed| passed.to_string());
+            text.push_str(&format!(
+                "exercise\t{}\t{}\t{}\t{}\n",
+                exercise.id, exercise.attempts, exercise.started, passed
+            ));
+        }
+
+        for quiz in &self.quizzes {
+            text.push_str(&format!(
+                "quiz\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                quiz.lesson,
+                quiz.taken,
+                quiz.last.0,
+                quiz.last.1,
+                quiz.best.0,
+                quiz.best.1,
+                quiz.last_taken
+            ));
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, text)?;
+        fs::rename(&temporary, &self.path)
+    }
+
+    pub fn lesson(&self, id: &str) -> Option<&LessonRecord> {
+        self.lessons.iter().find(|lesson| lesson.id == id)
+    }
+
+    pub fn exercise(&self, id: &str) -> Option<&ExerciseRecord> {
+        self.exercises.iter().find(|exercise| exercise.id == id)
+    }
+
+    pub fn quiz(&self, lesson: &str) -> Option<&QuizRecord> {
+        self.quizzes.iter().find(|quiz| quiz.lesson == lesson)
+    }
+
+    /// Whether the exercise `id` has been passed
+    pub fn passed(&self, id: &str) -> bool {
+        self.exercise(id).is_some_and(|e| e.passed.is_some())
+    }
+
+    pub fn lesson_run(&mut self, id: &str, now: u64) {
+        match self.lessons.iter_mut().find(|lesson| lesson.id == id) {
+            Some(lesson) => {
+                lesson.runs += 1;
+                lesson.last_run = now;
+            }
+            None => self.lessons.push(LessonRecord {
+                id: id.to_string(),
+                runs: 1,
+                last_run: now,
+            }),
+        }
+    }
+
+    /// Starts the clock on an exercise, unless it was started before
+    pub fn exercise_started(&mut self, id: &str, now: u64) -> &mut ExerciseRecord {
+        let index = match self.exercises.iter().position(|e| e.id == id) {
+            Some(index) => index,
+            None => {
+                self.exercises.push(ExerciseRecord {
+                    id: id.to_string(),
+                    attempts: 0,
+                    started: now,
+                    passed: None,
+                });
+                self.exercises.len() - 1
+            }
+        };
+
+        &mut self.exercises[index]
+    }
+
+    /// Records a check of an exercise. Checking a solved exercise again
+    /// adds neither to its attempts nor to the time it took
+    pub fn attempt(&mut self, id: &str, passed: bool, now: u64) {
+        let exercise = self.exercise_started(id, now);
+        if exercise.passed.is_some() {
+            return;
+        }
+
+        exercise.attempts += 1;
+        if passed {
+            exercise.passed = Some(now);
+        }
+    }
+
+    /// Records a quiz on `lesson` with `right` answers out of `asked`.
+    /// The best score is the one with the most right answers
+    pub fn quiz_taken(&mut self, lesson: &str, right: usize, asked: usize, now: u64) {
+        let score = (right, asked);
+
+        match self.quizzes.iter_mut().find(|quiz| quiz.lesson == lesson) {
+            Some(quiz) => {
+                quiz.taken += 1;
+                quiz.last = score;
+                if right > quiz.best.0 {
+                    quiz.best = score;
+                }
+                quiz.last_taken = now;
+            }
+            None => self.quizzes.push(QuizRecord {
+                lesson: lesson.to_string(),
+                taken: 1,
+                last: score,
+                best: score,
+                last_taken: now,
+            }),
+        }
+    }
+}
+
+/// Removes the progress file at `path`, returning whether there was one
+pub fn reset(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A number of seconds the way a person would say it, such as `2h 05m`
+pub fn duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if hours == 0 {
+        format!("{}m", minutes)
+    } else if hours < 48 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{} days", hours / 24)
+    }
+}
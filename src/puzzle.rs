@@ -0,0 +1,225 @@
+// Project: references
+// Author: Greg Folker
+
+// Borrow puzzles made up on the spot, in the style of the `r1`, `r2` and
+// `r3` examples in `references.rs`: a `String` and a random sequence of
+// `&` and `&mut` borrows of it, uses of those borrows, and blocks. Whether
+// a puzzle compiles is never worked out here. It is asked of the local
+// `rustc`, and the answer is kept in the verdict cache so that a puzzle
+// seen before does not have to be compiled again
+
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::Cache;
+
+/// A small xorshift generator, so that the same seed always makes the
+/// same puzzle without depending on a random number crate
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Xorshift never leaves zero, so zero is replaced by any other seed
+        Rng {
+            state: if seed == 0 {
+                0x2545_f491_4f6c_dd1d
+            } else {
+                seed
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A number from `0` up to, but not including, `n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// A seed that differs from one run to the next
+pub fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |since| since.as_nanos() as u64)
+        % 1_000_000
+}
+
+pub struct Puzzle {
+    /// The seed that makes this puzzle again
+    pub seed: u64,
+    pub source: String,
+}
+
+/// What `rustc` made of a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub compiles: bool,
+    /// Error codes such as `E0499`, in the order `rustc` reported them
+    pub codes: Vec<String>,
+    /// Whether the verdict was remembered rather than compiled just now
+    pub cached: bool,
+}
+
+// A reference that can still be used at some point in a puzzle
+struct Borrow {
+    name: String,
+    mutable: bool,
+    used: bool,
+}
+
+// Builds the body of `main`, one statement at a time
+struct Generator {
+    rng: Rng,
+    lines: Vec<String>,
+    // References visible in each enclosing block, innermost last
+    scopes: Vec<Vec<Borrow>>,
+    next: usize,
+}
+
+impl Generator {
+    fn indent(&self) -> String {
+        "    ".repeat(self.scopes.len())
+    }
+
+    fn push(&mut self, line: String) {
+        let line = format!("{}{}", self.indent(), line);
+        self.lines.push(line);
+    }
+
+    fn borrow(&mut self, mutable: bool) {
+        self.next += 1;
+        let name = format!("r{}", self.next);
+        self.push(format!(
+            "let {} = &{}s;",
+            name,
+            if mutable { "mut " } else { "" }
+        ));
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Borrow {
+                name,
+                mutable,
+                used: false,
+            });
+        }
+    }
+
+    // Uses a reference that is still in scope, borrowing anew if there
+    // is none
+    fn use_borrow(&mut self) {
+        let visible: Vec<(String, bool)> = self
+            .scopes
+            .iter()
+            .flatten()
+            .map(|borrow| (borrow.name.clone(), borrow.mutable))
+            .collect();
+        if visible.is_empty() {
+            let mutable = self.rng.below(2) == 0;
+            return self.borrow(mutable);
+        }
+
+        let (name, mutable) = visible[self.rng.below(visible.len())].clone();
+        self.mark_used(&name);
+        if mutable && self.rng.below(2) == 0 {
+            self.push(format!("{}.push_str(\"!\");", name));
+        } else {
+            self.push(format!("println!(\"{}={{}}\", {});", name, name));
+        }
+    }
+
+    fn mark_used(&mut self, name: &str) {
+        for borrow in self.scopes.iter_mut().flatten() {
+            if borrow.name == name {
+                borrow.used = true;
+            }
+        }
+    }
+
+    fn statement(&mut self, depth: usize) {
+        match self.rng.below(8) {
+            0 | 1 => self.borrow(false),
+            2 => self.borrow(true),
+            3..=5 => self.use_borrow(),
+            6 => {
+                if self.rng.below(2) == 0 {
+                    self.push(String::from("println!(\"s={}\", s);"));
+                } else {
+                    self.push(String::from("s.push_str(\"!\");"));
+                }
+            }
+            _ if depth == 0 => {
+                self.push(String::from("{"));
+                self.scopes.push(Vec::new());
+                for _ in 0..1 + self.rng.below(3) {
+                    self.statement(depth + 1);
+                }
+                self.scopes.pop();
+                self.push(String::from("}"));
+            }
+            _ => self.use_borrow(),
+        }
+    }
+}
+
+/// The puzzle made by `seed`
+pub fn generate(seed: u64) -> Puzzle {
+    let mut generator = Generator {
+        rng: Rng::new(seed),
+        lines: Vec::new(),
+        scopes: vec![Vec::new()],
+        next: 0,
+    };
+
+    generator.push(String::from("let mut s = String::from(\"Hello\");"));
+    generator.lines.push(String::new());
+    for _ in 0..4 + generator.rng.below(4) {
+        generator.statement(0);
+    }
+
+    // A borrow that is never used ends as soon as it is taken, which
+    // makes for dull puzzles, so most of them are used once more at the end
+    let unused: Vec<String> = generator.scopes[0]
+        .iter()
+        .filter(|borrow| !borrow.used)
+        .map(|borrow| borrow.name.clone())
+        .collect();
+    for name in unused {
+        if generator.rng.below(3) != 0 {
+            generator.push(format!("println!(\"{}={{}}\", {});", name, name));
+        }
+    }
+
+    let mut source = String::from("fn main() {\n");
+    for line in &generator.lines {
+        source.push_str(line);
+        source.push('\n');
+    }
+    source.push_str("}\n");
+
+    Puzzle { seed, source }
+}
+
+impl Puzzle {
+    /// Asks `rustc` whether the puzzle compiles, unless `cache` already
+    /// knows. Verdicts are cached by the version of `rustc` as well as the
+    /// puzzle, since a newer compiler may accept more programs
+    pub fn verdict(&self, cache: &Cache) -> io::Result<Verdict> {
+        let outcome = cache.compile("puzzle", &self.source)?;
+
+        Ok(Verdict {
+            compiles: outcome.success,
+            codes: outcome.codes,
+            cached: outcome.cached,
+        })
+    }
+}
@@ -0,0 +1,222 @@
+// Project: references
+// Author: Greg Folker
+
+// Questions about the lessons and their commented-out errors: does this
+// compile, which error does `rustc` give, and what does this print. None
+// of the answers are written down here. Whether a snippet compiles, and
+// with which error, is asked of the local `rustc` when the question is
+// answered, and what a lesson prints comes from its recorded snapshot
+
+use std::io;
+
+use crate::cache::Cache;
+use crate::compile;
+use crate::examples;
+use crate::lesson::{self, Lesson};
+use crate::snapshot;
+use crate::source::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Does the snippet compile?
+    Compiles,
+    /// Which error code does `rustc` reject the snippet with?
+    ErrorCode,
+    /// What does the snippet print?
+    Prints,
+}
+
+pub struct Question {
+    pub kind: Kind,
+    pub lesson: &'static Lesson,
+    /// The code shown to the student, without the comments that would
+    /// give the answer away
+    pub snippet: String,
+    /// The complete program the snippet comes from, which is compiled to
+    /// find the answer
+    pub program: String,
+}
+
+/// Whether an answer was right, along with the right answer
+pub struct Verdict {
+    pub correct: bool,
+    pub answer: String,
+}
+
+/// The questions about a lesson: whether its code compiles and what it
+/// prints, then for each of its commented-out errors whether that
+/// compiles and which error it gives
+pub fn questions(lesson: &'static Lesson) -> Result<Vec<Question>, Error> {
+    let program = lesson.program()?;
+    let snippet = without_comments(&program);
+    let mut questions = vec![
+        Question {
+            kind: Kind::Compiles,
+            lesson,
+            snippet: snippet.clone(),
+            program: program.clone(),
+        },
+        Question {
+            kind: Kind::Prints,
+            lesson,
+            snippet,
+            program,
+        },
+    ];
+
+    for example in examples::all()? {
+        if lesson::for_example(&example).map(|l| l.id) != Some(lesson.id) {
+            continue;
+        }
+
+        // An error outside any function leaves `main` empty, so it runs
+        // the lesson that uses it instead
+        let mut program = example.program();
+        if program.contains("fn main() {}") {
+            program = program.replace("fn main() {}", &lesson.as_main()?);
+        }
+        let snippet = without_comments(&program);
+
+        questions.push(Question {
+            kind: Kind::Compiles,
+            lesson,
+            snippet: snippet.clone(),
+            program: program.clone(),
+        });
+        questions.push(Question {
+            kind: Kind::ErrorCode,
+            lesson,
+            snippet,
+            program,
+        });
+    }
+
+    Ok(questions)
+}
+
+/// Compiles every program the questions ask about at once, in parallel,
+/// so that checking each answer finds its verdict already in `cache`
+pub fn prepare(questions: &[Question], cache: &Cache) -> io::Result<()> {
+    let mut programs: Vec<(&str, &str)> = Vec::new();
+    for question in questions {
+        let program = ("quiz", question.program.as_str());
+        if question.kind != Kind::Prints && !programs.contains(&program) {
+            programs.push(program);
+        }
+    }
+
+    cache.compile_all(&programs).map(|_| ())
+}
+
+impl Question {
+    /// What the student is asked. Asking for the error code says whether
+    /// `rustc` compiles the snippet, since the question before asked that
+    pub fn prompt(&self) -> io::Result<&'static str> {
+        Ok(match self.kind {
+            Kind::Compiles => "Does this compile? (yes or no)",
+            Kind::ErrorCode if compile::compile("quiz", &self.program)?.success => {
+                "Which error code does `rustc` give, if any? (such as E0000, or none)"
+            }
+            Kind::ErrorCode => {
+                "It does not compile. Which error code does `rustc` give? (such as E0000)"
+            }
+            Kind::Prints => "What does this print? Type each line, then an empty line",
+        })
+    }
+
+    /// Checks `given` against what `rustc` or the recorded output says
+    pub fn check(&self, given: &str) -> io::Result<Verdict> {
+        match self.kind {
+            Kind::Compiles => {
+                let outcome = compile::compile("quiz", &self.program)?;
+                let said_yes = matches!(given.trim().to_lowercase().as_str(), "y" | "yes");
+                let answer = if outcome.success {
+                    String::from("yes, `rustc` compiles it")
+                } else {
+                    // Which error it is may be the next question
+                    String::from("no, `rustc` rejects it")
+                };
+
+                Ok(Verdict {
+                    correct: said_yes == outcome.success,
+                    answer,
+                })
+            }
+            Kind::ErrorCode => {
+                let outcome = compile::compile("quiz", &self.program)?;
+                let given = given.trim().to_uppercase();
+                if outcome.success {
+                    return Ok(Verdict {
+                        correct: matches!(given.as_str(), "" | "NONE"),
+                        answer: String::from("none, `rustc` compiles it"),
+                    });
+                }
+                let given = if given.starts_with('E') {
+                    given
+                } else {
+                    format!("E{}", given)
+                };
+                let message = outcome
+                    .stderr
+                    .lines()
+                    .find(|line| line.starts_with("error["))
+                    .or_else(|| {
+                        outcome
+                            .stderr
+                            .lines()
+                            .find(|line| line.starts_with("error"))
+                    })
+                    .unwrap_or_default();
+
+                Ok(Verdict {
+                    correct: outcome.codes.contains(&given),
+                    answer: message.to_string(),
+                })
+            }
+            Kind::Prints => {
+                let expected = snapshot::expected(self.lesson).ok_or_else(|| {
+                    io::Error::other(format!("no recorded output for '{}'", self.lesson.id))
+                })?;
+                let lines = |text: &str| -> Vec<String> {
+                    text.lines()
+                        .map(|line| line.trim().to_string())
+                        .filter(|line| !line.is_empty())
+                        .collect()
+                };
+                let answer = expected
+                    .lines()
+                    .map(|line| format!("    {}", line))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+
+                Ok(Verdict {
+                    correct: lines(given) == lines(expected),
+                    answer: format!("it prints\n{}", answer),
+                })
+            }
+        }
+    }
+}
+
+// `program` without comments or attributes, and with no more than one
+// blank line in a row or any at the start of a block
+fn without_comments(program: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+
+    for line in program.lines() {
+        if line.starts_with("#!") || line.trim_start().starts_with("//") {
+            continue;
+        }
+        let line = match line.find("//") {
+            Some(at) => line[..at].trim_end(),
+            None => line,
+        };
+        let previous = out.last().map_or("", |last| last.trim());
+        if line.trim().is_empty() && (previous.is_empty() || previous.ends_with('{')) {
+            continue;
+        }
+        out.push(line);
+    }
+
+    out.join("\n").trim().to_string()
+}
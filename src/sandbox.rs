@@ -0,0 +1,310 @@
+// Project: references
+// Author: Greg Folker
+
+// Running code a student wrote, or the compiler on it, without letting it
+// take the machine down with it. Each run happens in a directory of its
+// own with an empty environment, under limits on CPU time, memory, the
+// size of files it writes and how long it may take. A run that goes over
+// its time is killed along with every process it started, as is anything
+// a run leaves running when it ends, and no more than a set amount of its
+// output is kept
+//
+// The limits on CPU time, memory and file size are set with `setrlimit`
+// on Linux and macOS. Elsewhere only the time and output limits apply
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a run may use before it is stopped
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Seconds of CPU time
+    pub cpu_seconds: u64,
+    /// Bytes of address space
+    pub memory_bytes: u64,
+    /// Bytes kept of each of stdout and stderr
+    pub output_bytes: usize,
+    /// Bytes in the largest file the run may write
+    pub file_bytes: u64,
+    /// How long the run may take, start to finish
+    pub wall_clock: Duration,
+}
+
+/// Limits for a lesson-sized program, which finishes in well under a
+/// second and prints a few lines
+pub const PROGRAM: Limits = Limits {
+    cpu_seconds: 5,
+    memory_bytes: 512 << 20,
+    output_bytes: 64 << 10,
+    file_bytes: 1 << 20,
+    wall_clock: Duration::from_secs(10),
+};
+
+/// Limits for `rustc` compiling such a program
+pub const COMPILER: Limits = Limits {
+    cpu_seconds: 60,
+    memory_bytes: 4 << 30,
+    output_bytes: 1 << 20,
+    file_bytes: 256 << 20,
+    wall_clock: Duration::from_secs(120),
+};
+
+// What the compiler needs from the environment to be found and to find
+// the linker. Nothing else is passed on, so secrets in the environment
+// never reach a student's code
+const COMPILER_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_HOME",
+    "SYSTEMROOT",
+];
+
+/// Arguments for a program that takes none
+pub const NO_ARGS: [&str; 0] = [];
+
+/// How a run ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// It exited with this code
+    Code(i32),
+    /// It was killed by this signal, such as for using too much CPU time
+    Signal(i32),
+    /// It was still running when its time was up, and was killed
+    TimedOut,
+}
+
+pub struct Run {
+    pub exit: Exit,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether output past the limit was thrown away
+    pub truncated: bool,
+}
+
+impl Run {
+    pub fn success(&self) -> bool {
+        self.exit == Exit::Code(0)
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exit::Code(code) => write!(f, "exited with status {}", code),
+            Exit::Signal(SIGXCPU) => write!(f, "was stopped for using too much CPU time"),
+            Exit::Signal(SIGXFSZ) => write!(f, "was stopped for writing too large a file"),
+            Exit::Signal(SIGKILL) => write!(f, "was killed"),
+            Exit::Signal(signal) => write!(f, "was killed by signal {}", signal),
+            Exit::TimedOut => write!(f, "was killed for taking too long"),
+        }
+    }
+}
+
+/// Where and how a command is run
+pub struct Sandbox {
+    pub limits: Limits,
+    // Names of environment variables passed on from this process
+    env: &'static [&'static str],
+}
+
+impl Sandbox {
+    /// A sandbox for a student's program, which gets no environment at all
+    pub fn program() -> Sandbox {
+        Sandbox::new(PROGRAM, &[])
+    }
+
+    /// A sandbox for `rustc`, which gets only what it needs to run
+    pub fn compiler() -> Sandbox {
+        Sandbox::new(COMPILER, COMPILER_ENV)
+    }
+
+    pub fn new(limits: Limits, env: &'static [&'static str]) -> Sandbox {
+        Sandbox { limits, env }
+    }
+
+    /// Runs `program` with `args` in `dir`, which should be a directory
+    /// made for the run, and waits for it to finish or run out of time
+    pub fn run<I, S>(&self, program: impl AsRef<OsStr>, args: I, dir: &Path) -> io::Result<Run>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(dir)
+            .env_clear()
+            .env("TMPDIR", dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for name in self.env {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        limit(&mut command, self.limits);
+
+        let mut child = command.spawn()?;
+        let stdout = capture(child.stdout.take(), self.limits.output_bytes);
+        let stderr = capture(child.stderr.take(), self.limits.output_bytes);
+
+        let exit = wait(&mut child, self.limits.wall_clock)?;
+        // Anything the run left behind would hold the pipes open, and the
+        // output would not end until it did
+        kill_group(&mut child);
+        let (stdout, stdout_truncated) = stdout.join().unwrap_or_default();
+        let (stderr, stderr_truncated) = stderr.join().unwrap_or_default();
+
+        Ok(Run {
+            exit,
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+        })
+    }
+}
+
+// Reads a pipe on a thread of its own, so that a run filling one pipe
+// while nothing reads it cannot stall. Past `limit` the pipe is closed,
+// and a program that keeps writing to it fails
+fn capture<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    limit: usize,
+) -> thread::JoinHandle<(String, bool)> {
+    thread::spawn(move || {
+        let mut pipe = match pipe {
+            Some(pipe) => pipe,
+            None => return (String::new(), false),
+        };
+
+        let mut kept = Vec::new();
+        let read = pipe.by_ref().take(limit as u64 + 1).read_to_end(&mut kept);
+        let truncated = read.is_ok() && kept.len() > limit;
+        kept.truncate(limit);
+
+        (String::from_utf8_lossy(&kept).into_owned(), truncated)
+    })
+}
+
+fn wait(child: &mut Child, timeout: Duration) -> io::Result<Exit> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(exit(status));
+        }
+        if Instant::now() >= deadline {
+            kill_group(child);
+            child.wait()?;
+            return Ok(Exit::TimedOut);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(unix)]
+fn exit(status: ExitStatus) -> Exit {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (Some(code), _) => Exit::Code(code),
+        (None, Some(signal)) => Exit::Signal(signal),
+        (None, None) => Exit::Code(-1),
+    }
+}
+
+#[cfg(not(unix))]
+fn exit(status: ExitStatus) -> Exit {
+    Exit::Code(status.code().unwrap_or(-1))
+}
+
+// The same on Linux and macOS
+const SIGKILL: i32 = 9;
+const SIGXCPU: i32 = 24;
+const SIGXFSZ: i32 = 25;
+
+// Resource numbers for `setrlimit`, which differ between systems
+#[cfg(target_os = "linux")]
+mod resource {
+    pub const CPU: i32 = 0;
+    pub const FSIZE: i32 = 1;
+    pub const AS: i32 = 9;
+}
+
+#[cfg(target_os = "macos")]
+mod resource {
+    pub const CPU: i32 = 0;
+    pub const FSIZE: i32 = 1;
+    pub const AS: i32 = 5;
+}
+
+#[cfg(unix)]
+#[repr(C)]
+struct Rlimit {
+    current: u64,
+    maximum: u64,
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn setrlimit(resource: i32, limit: *const Rlimit) -> i32;
+    fn kill(pid: i32, signal: i32) -> i32;
+}
+
+// Puts the run in a process group of its own, so that everything it
+// starts can be killed together, and sets its resource limits
+#[cfg(unix)]
+fn limit(command: &mut Command, limits: Limits) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let settings = [
+            // Past the soft limit the run gets SIGXCPU, and a second
+            // later SIGKILL in case it ignores that
+            (resource::CPU, limits.cpu_seconds, limits.cpu_seconds + 1),
+            (resource::AS, limits.memory_bytes, limits.memory_bytes),
+            (resource::FSIZE, limits.file_bytes, limits.file_bytes),
+        ];
+
+        // Only `setrlimit` is called between `fork` and `exec`, which is
+        // safe to do there
+        unsafe {
+            command.pre_exec(move || {
+                for (resource, current, maximum) in settings {
+                    let limit = Rlimit { current, maximum };
+                    if setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn limit(_: &mut Command, _: Limits) {}
+
+#[cfg(unix)]
+fn kill_group(child: &mut Child) {
+    // The run leads its own group, so the group has its process id
+    unsafe {
+        kill(-(child.id() as i32), SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(child: &mut Child) {
+    let _ = child.kill();
+}
@@ -0,0 +1,157 @@
+// Project: references
+// Author: Greg Folker
+
+// Expected output for every lesson, kept next to the lessons in
+// `src/snapshots/<lesson>.stdout` and built into the binary, so that an
+// installed copy still knows what the lessons print. Comparing and
+// recording use the files themselves
+//
+// Lessons print with `println!` just like the original `main()` did, so
+// their output is captured by running the `references` binary itself
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::lesson::Lesson;
+use crate::progress;
+
+/// How a lesson's output compares with its snapshot
+pub enum Status {
+    Matches,
+    /// The output changed, along with a diff from the snapshot to the output
+    Differs(String),
+    /// There is no snapshot for the lesson yet
+    Missing,
+}
+
+// The recorded output of each lesson, by lesson id
+const RECORDED: &[(&str, &str)] = &[
+    ("borrowing", include_str!("snapshots/borrowing.stdout")),
+    (
+        "mutable-references",
+        include_str!("snapshots/mutable-references.stdout"),
+    ),
+    (
+        "mutable-aliasing",
+        include_str!("snapshots/mutable-aliasing.stdout"),
+    ),
+    (
+        "scoped-borrows",
+        include_str!("snapshots/scoped-borrows.stdout"),
+    ),
+    (
+        "shared-and-mutable",
+        include_str!("snapshots/shared-and-mutable.stdout"),
+    ),
+    (
+        "dangling-references",
+        include_str!("snapshots/dangling-references.stdout"),
+    ),
+];
+
+pub fn path(lesson: &Lesson) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("snapshots")
+        .join(format!("{}.stdout", lesson.id))
+}
+
+/// The recorded output of `lesson` as it was when the binary was built,
+/// if it had been recorded
+pub fn expected(lesson: &Lesson) -> Option<&'static str> {
+    RECORDED
+        .iter()
+        .find(|(id, _)| *id == lesson.id)
+        .map(|(_, output)| *output)
+}
+
+/// The recorded output of `lesson` in `src/snapshots`, which may have
+/// been recorded again since the binary was built
+pub fn recorded(lesson: &Lesson) -> io::Result<Option<String>> {
+    match fs::read_to_string(path(lesson)) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs `lesson` through the `references` binary at `exe` and returns
+/// everything it printed
+pub fn capture(exe: &Path, lesson: &Lesson) -> io::Result<String> {
+    // Comparing output is not the student running the lesson
+    let output = Command::new(exe)
+        .arg("run")
+        .arg(lesson.id)
+        .env(progress::DISABLE_VARIABLE, "1")
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`{} run {}` failed with {}:\n{}",
+            exe.display(),
+            lesson.id,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn compare(lesson: &Lesson, actual: &str) -> io::Result<Status> {
+    Ok(match recorded(lesson)? {
+        None => Status::Missing,
+        Some(expected) if expected == actual => Status::Matches,
+        Some(expected) => Status::Differs(diff(&expected, actual)),
+    })
+}
+
+/// Records `output` as the expected output of `lesson`
+pub fn bless(lesson: &Lesson, output: &str) -> io::Result<()> {
+    let path = path(lesson);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, output)
+}
+
+/// A line by line diff from `old` to `new`, with removed lines marked `-`,
+/// added lines marked `+` and unchanged lines indented to line up
+pub fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common run of lines
+    // between old[i..] and new[j..]
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push_str(&format!("  {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+    }
+
+    diff
+}
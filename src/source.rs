@@ -0,0 +1,179 @@
+// Project: references
+// Author: Greg Folker
+
+// Helpers for pulling pieces of the lesson source files back out so they
+// can be printed next to the lessons that use them
+
+use std::fmt;
+
+use crate::anchors;
+
+/// The source of `references.rs`, exactly as the lessons were written
+pub const REFERENCES: &str = include_str!("references.rs");
+pub const REFERENCES_PATH: &str = "src/references.rs";
+
+/// Every lesson file, by its path relative to the crate root
+pub const SOURCES: &[(&str, &str)] = &[(REFERENCES_PATH, REFERENCES)];
+
+/// Something in a lesson file that could not be made sense of, such as a
+/// malformed marker or a reference to an anchor that does not exist
+#[derive(Debug)]
+pub struct Error {
+    pub file: &'static str,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Text that does not live in the file itself, such as a lesson's
+        // narration, has no line to point at
+        match self.line {
+            0 => write!(f, "{}: {}", self.file, self.message),
+            line => write!(f, "{}:{}: {}", self.file, line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// `text` with every `{@anchor}` replaced by the line it names and the
+/// anchors themselves taken out, leaving every line where it was
+pub fn resolved(file: &'static str, text: &str) -> Result<String, Error> {
+    let anchors = anchors::collect(file, text)?;
+    anchors::resolve(file, text, &anchors)
+}
+
+/// Returns every definition of the function `name` in `source`, in the
+/// order they appear, including the comments directly above each one.
+/// Commented-out definitions such as `// fn dangle() -> &String {` are
+/// returned as well, running up to their commented-out closing bracket
+pub fn items<'a>(source: &'a str, name: &str) -> Vec<&'a str> {
+    let lines: Vec<&str> = source.lines().collect();
+    let offsets = line_offsets(source);
+
+    item_lines(source, name)
+        .into_iter()
+        .map(|(start, end)| source[offsets[start]..offsets[end] + lines[end].len()].trim_end())
+        .collect()
+}
+
+/// The first and last line, counting from 0, of each definition `items`
+/// returns
+pub fn item_lines(source: &str, name: &str) -> Vec<(usize, usize)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut found = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let commented = match definition(line, name) {
+            Some(commented) => commented,
+            None => continue,
+        };
+
+        let end = if commented {
+            match lines[i..].iter().position(|l| l.trim_end() == "// }") {
+                Some(n) => i + n,
+                None => continue,
+            }
+        } else {
+            match closing_line(&lines[i..]) {
+                Some(n) => trailing_comments(&lines, i + n),
+                None => continue,
+            }
+        };
+
+        found.push((leading_comments(&lines, i), end));
+    }
+
+    found
+}
+
+/// The first and last line, counting from 0, of the definition of the
+/// function `name` that is not commented out, from its signature to its
+/// closing bracket
+pub fn definition_lines(source: &str, name: &str) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| definition(line, name) == Some(false))?;
+
+    closing_line(&lines[start..]).map(|n| (start, start + n))
+}
+
+/// Checks whether `line` starts a definition of `name`, returning whether
+/// that definition is commented out
+fn definition(line: &str, name: &str) -> Option<bool> {
+    let (commented, rest) = match line.strip_prefix("// ") {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let rest = rest.strip_prefix("pub ").unwrap_or(rest);
+
+    if rest
+        .strip_prefix("fn ")?
+        .strip_prefix(name)?
+        .starts_with('(')
+    {
+        Some(commented)
+    } else {
+        None
+    }
+}
+
+/// Byte offset at which each line of `source` starts
+pub fn line_offsets(source: &str) -> Vec<usize> {
+    let mut offsets = vec![0];
+    offsets.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    offsets
+}
+
+/// Index of the line holding the bracket that closes the first opening
+/// bracket in `lines`, skipping brackets inside strings and comments
+pub(crate) fn closing_line(lines: &[&str]) -> Option<usize> {
+    let mut depth = 0;
+    let mut opened = false;
+
+    for (n, line) in lines.iter().enumerate() {
+        let mut chars = line.chars();
+        let mut in_string = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if in_string => {
+                    chars.next();
+                }
+                '"' => in_string = !in_string,
+                '/' if !in_string && chars.as_str().starts_with('/') => break,
+                '{' if !in_string => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' if !in_string => {
+                    depth -= 1;
+                    if opened && depth == 0 {
+                        return Some(n);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+fn leading_comments(lines: &[&str], mut start: usize) -> usize {
+    while start > 0 && lines[start - 1].trim_start().starts_with("//") {
+        start -= 1;
+    }
+    start
+}
+
+// Comments that continue a closing bracket, such as the ones after
+// `calculate_length`, are indented underneath it
+fn trailing_comments(lines: &[&str], mut end: usize) -> usize {
+    while end + 1 < lines.len() && lines[end + 1].starts_with("  //") {
+        end += 1;
+    }
+    end
+}
@@ -0,0 +1,43 @@
+// Project: references
+// Author: Greg Folker
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A scratch directory under the system temp directory that is removed
+/// again when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(prefix: &str) -> io::Result<TempDir> {
+        let n = NEXT.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("{}-{}-{}", prefix, process::id(), n));
+
+        // A leftover directory from an earlier process with the same pid
+        // is of no use to anyone
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+
+        Ok(TempDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,329 @@
+// Project: references
+// Author: Greg Folker
+
+// A line by line picture of when each reference in a lesson is created,
+// last used and dead, to make non-lexical lifetimes visible. A borrow
+// lasts from where it is taken to the last line that uses it, not to the
+// end of the block it was declared in
+//
+// The commented-out `@compile_fail` lines are drawn as well, showing which
+// live borrows the reference they would create overlaps with
+
+use crate::lesson::Lesson;
+use crate::source::{self, Error};
+
+const WIDTH: usize = 6;
+
+// A reference taken somewhere in the lesson
+struct Borrow {
+    name: String,
+    owner: String,
+    mutable: bool,
+    created: usize,
+    last_use: usize,
+    // A borrow from a commented-out `@compile_fail` line
+    hypothetical: bool,
+}
+
+// One line of the lesson as it is drawn
+struct Row {
+    number: usize,
+    code: String,
+    hypothetical: bool,
+}
+
+pub fn render(lesson: &Lesson) -> Result<String, Error> {
+    let text = source::resolved(source::REFERENCES_PATH, source::REFERENCES)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let (start, end) = source::definition_lines(&text, lesson.function).ok_or_else(|| Error {
+        file: source::REFERENCES_PATH,
+        line: 0,
+        message: format!("there is no function named `{}`", lesson.function),
+    })?;
+
+    let rows = rows(&lines[start + 1..end], start + 2);
+    let borrows = borrows(&rows);
+
+    if borrows.is_empty() {
+        return Ok(String::from("No references are taken in this lesson\n"));
+    }
+
+    let code_width = rows.iter().map(|row| row.code.len()).max().unwrap_or(0);
+    let mut out = String::new();
+
+    out.push_str(&format!(
+        "{:>4}  {:code_width$}",
+        "line",
+        "",
+        code_width = code_width
+    ));
+    for borrow in &borrows {
+        out.push_str(&format!("  {:WIDTH$}", borrow.name, WIDTH = WIDTH));
+    }
+    out.push('\n');
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut line = format!(
+            "{:>4}  {:code_width$}",
+            row.number,
+            row.code,
+            code_width = code_width
+        );
+
+        for borrow in &borrows {
+            line.push_str(&format!("  {:WIDTH$}", cell(borrow, i), WIDTH = WIDTH));
+        }
+
+        if row.hypothetical {
+            line.push_str(&conflicts(&borrows, i));
+        }
+
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    out.push_str(
+        "\n&, &mut  borrow taken    |  borrow live    *  last use    !  would not compile\n",
+    );
+
+    Ok(out)
+}
+
+// The lines of code in a function body, along with any `@compile_fail`
+// lines, which are drawn still commented out
+fn rows(body: &[&str], first_number: usize) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut after_marker = false;
+
+    for (i, line) in body.iter().enumerate() {
+        let trimmed = line.trim();
+        let comment = trimmed.starts_with("//");
+
+        if comment && trimmed.contains("@compile_fail") {
+            after_marker = true;
+            continue;
+        }
+
+        let hypothetical = comment && after_marker;
+        after_marker = false;
+
+        if trimmed.is_empty() || (comment && !hypothetical) {
+            continue;
+        }
+
+        // Keep the indentation inside the function so blocks stand out
+        let code = if hypothetical {
+            trimmed
+        } else {
+            without_comment(line)
+        };
+        let code = code.strip_prefix("    ").unwrap_or(code);
+
+        rows.push(Row {
+            number: first_number + i,
+            code: code.trim_end().to_string(),
+            hypothetical,
+        });
+    }
+
+    rows
+}
+
+fn borrows(rows: &[Row]) -> Vec<Borrow> {
+    let mut borrows: Vec<Borrow> = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        let code = code_only(row.code.trim().trim_start_matches('/').trim());
+
+        // `let r1 = &s;` and `let r1 = &mut s;`
+        let named = code
+            .strip_prefix("let ")
+            .and_then(|rest| rest.split_once('='))
+            .map(|(name, value)| (name.trim(), value.trim().trim_end_matches(';')))
+            .and_then(|(name, value)| Some((name, target(value)?)));
+
+        if let Some((name, (mutable, owner))) = named {
+            let name = if row.hypothetical {
+                format!("{}?", name)
+            } else {
+                name.to_string()
+            };
+
+            borrows.push(Borrow {
+                name,
+                owner: owner.to_string(),
+                mutable,
+                created: i,
+                last_use: i,
+                hypothetical: row.hypothetical,
+            });
+            continue;
+        }
+
+        if row.hypothetical {
+            continue;
+        }
+
+        // Anything else that takes a reference, such as `change(&mut s)`,
+        // only borrows for the length of that line
+        let mut rest = code.as_str();
+        while let Some(at) = rest.find('&') {
+            rest = &rest[at..];
+            let end = rest[1..].find([')', ',']).map_or(rest.len(), |n| n + 1);
+
+            if let Some((mutable, owner)) = target(&rest[..end]) {
+                borrows.push(Borrow {
+                    name: rest[..end].to_string(),
+                    owner: owner.to_string(),
+                    mutable,
+                    created: i,
+                    last_use: i,
+                    hypothetical: false,
+                });
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    // A borrow is used up until its name is bound to something else
+    for b in 0..borrows.len() {
+        if borrows[b].hypothetical || borrows[b].name.starts_with('&') {
+            continue;
+        }
+
+        let rebound = borrows[b + 1..]
+            .iter()
+            .find(|other| other.name == borrows[b].name)
+            .map_or(rows.len(), |other| other.created);
+
+        for (i, row) in rows
+            .iter()
+            .enumerate()
+            .take(rebound)
+            .skip(borrows[b].created + 1)
+        {
+            if !row.hypothetical && uses(&code_only(&row.code), &borrows[b].name) {
+                borrows[b].last_use = i;
+            }
+        }
+    }
+
+    borrows
+}
+
+// `&s` or `&mut s`, as whether the borrow is mutable and what it borrows
+fn target(value: &str) -> Option<(bool, &str)> {
+    let value = value.strip_prefix('&')?;
+    let (mutable, owner) = match value.strip_prefix("mut ") {
+        Some(owner) => (true, owner.trim()),
+        None => (false, value.trim()),
+    };
+
+    if !owner.is_empty() && owner.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        Some((mutable, owner))
+    } else {
+        None
+    }
+}
+
+fn cell(borrow: &Borrow, row: usize) -> &'static str {
+    if row == borrow.created {
+        if borrow.hypothetical {
+            "!"
+        } else if borrow.mutable {
+            "&mut"
+        } else {
+            "&"
+        }
+    } else if borrow.hypothetical || row > borrow.last_use || row < borrow.created {
+        ""
+    } else if row == borrow.last_use {
+        "*"
+    } else {
+        "|"
+    }
+}
+
+// What the borrow on a `@compile_fail` line would overlap with
+fn conflicts(borrows: &[Borrow], row: usize) -> String {
+    let new = match borrows.iter().find(|b| b.hypothetical && b.created == row) {
+        Some(new) => new,
+        None => return String::new(),
+    };
+
+    let live: Vec<&Borrow> = borrows
+        .iter()
+        .filter(|b| !b.hypothetical && b.owner == new.owner)
+        .filter(|b| b.created < row && row < b.last_use)
+        .filter(|b| b.mutable || new.mutable)
+        .collect();
+
+    if live.is_empty() {
+        return String::from("  <- compiles, nothing live overlaps it");
+    }
+
+    let names: Vec<&str> = live.iter().map(|b| b.name.as_str()).collect();
+    let code = if new.mutable && live.iter().all(|b| b.mutable) {
+        "E0499"
+    } else {
+        "E0502"
+    };
+
+    format!(
+        "  <- overlaps {} while still live ({})",
+        names.join(", "),
+        code
+    )
+}
+
+// Whether `code` mentions the variable `name` as a whole word
+fn uses(code: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    code.match_indices(name).any(|(i, _)| {
+        !code[..i].ends_with(is_ident) && !code[i + name.len()..].starts_with(is_ident)
+    })
+}
+
+// `line` up to any trailing comment
+fn without_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '/' if !in_string && line[i + 1..].starts_with('/') => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+// `line` with the contents of string literals and any trailing comment
+// removed, so `println!("r1={}", r1)` only mentions `r1` once
+fn code_only(line: &str) -> String {
+    let mut code = String::new();
+    let mut chars = line.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => {
+                in_string = !in_string;
+                code.push(c);
+            }
+            '/' if !in_string && chars.peek() == Some(&'/') => break,
+            _ if in_string => {}
+            _ => code.push(c),
+        }
+    }
+
+    code
+}
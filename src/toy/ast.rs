@@ -0,0 +1,124 @@
+// Project: references
+// Author: Greg Folker
+
+// The syntax tree of a toy program. Every node keeps the span of source
+// it was parsed from so diagnostics can point back at it
+
+/// A range of bytes in the program's source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    String,
+    Usize,
+    /// A string literal, `&'static str` in real Rust
+    Str,
+    Unit,
+    Ref(bool, Box<Type>),
+}
+
+impl Type {
+    pub fn is_ref(&self) -> bool {
+        matches!(self, Type::Ref(..))
+    }
+
+    /// Types that are copied rather than moved when used by value
+    pub fn is_copy(&self) -> bool {
+        match self {
+            Type::Usize | Type::Str | Type::Unit => true,
+            Type::Ref(mutable, _) => !mutable,
+            Type::String => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::String => write!(f, "String"),
+            Type::Usize => write!(f, "usize"),
+            Type::Str => write!(f, "&str"),
+            Type::Unit => write!(f, "()"),
+            Type::Ref(false, inner) => write!(f, "&{}", inner),
+            Type::Ref(true, inner) => write!(f, "&mut {}", inner),
+        }
+    }
+}
+
+pub struct Function {
+    pub name: String,
+    pub name_span: Span,
+    pub params: Vec<Param>,
+    pub ret: Option<(Type, Span)>,
+    pub body: Block,
+}
+
+pub struct Param {
+    pub name: String,
+    pub span: Span,
+    pub ty: Type,
+    pub ty_span: Span,
+}
+
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    /// The final expression without a semicolon, which is the block's value
+    pub tail: Option<Expr>,
+    pub span: Span,
+}
+
+pub enum Stmt {
+    Let {
+        name: String,
+        mutable: bool,
+        name_span: Span,
+        init: Expr,
+        span: Span,
+    },
+    Expr(Expr),
+    Block(Block),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let { span, .. } => *span,
+            Stmt::Expr(expr) => expr.span,
+            Stmt::Block(block) => block.span,
+        }
+    }
+}
+
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+pub enum ExprKind {
+    /// `String::from("...")`
+    StringFrom(String),
+    /// A string literal on its own
+    Str(String),
+    Int(usize),
+    Var(String),
+    /// `&place` or `&mut place`
+    Ref(bool, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// `receiver.method(args)`, only `push_str` and `len` are understood
+    Method(Box<Expr>, String, Vec<Expr>),
+    /// `println!("...", args)`
+    Println(String, Vec<Expr>),
+}
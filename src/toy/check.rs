@@ -0,0 +1,987 @@
+// Project: references
+// Author: Greg Folker
+
+// The borrow checker for toy programs
+//
+// Checking happens in the same phases as in `rustc`, and stops after the
+// first phase that finds an error, so a program gets the errors `rustc`
+// would show for it:
+//
+//  1. Function signatures and names (E0106, E0425)
+//  2. Types (E0308, E0061, E0599)
+//  3. Ownership and borrowing (E0382, E0499, E0502, E0505, E0515, E0596)
+//
+// Programs have no loops or branches, so every statement in a function is
+// given a point in the order it runs. A borrow lives from the point it is
+// taken to the last point that uses a reference holding it, which is how
+// non-lexical lifetimes end a borrow after its last use
+
+use std::collections::HashMap;
+
+use super::ast::{Block, Expr, ExprKind, Function, Span, Stmt, Type};
+use super::diagnostic::{position, Diagnostic};
+use super::Program;
+
+/// The outcome of checking a program
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+    /// What the checker concluded at each step, in plain words
+    pub steps: Vec<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+pub fn check(program: &Program) -> Report {
+    let mut report = Report {
+        diagnostics: Vec::new(),
+        steps: Vec::new(),
+    };
+
+    // Phase 1: signatures and names
+    for function in &program.functions {
+        signature(program, function, &mut report);
+    }
+    let resolutions: Vec<Resolution> = program
+        .functions
+        .iter()
+        .map(|function| resolve(program, function, &mut report.diagnostics))
+        .collect();
+    if !report.is_ok() {
+        return report;
+    }
+
+    // Phases 2 and 3 run together, but borrowing errors are only reported
+    // once the types are known to be right
+    let mut type_errors = Vec::new();
+    let mut borrow_errors = Vec::new();
+    for (function, resolution) in program.functions.iter().zip(&resolutions) {
+        let mut checker = Checker {
+            program,
+            function,
+            resolution,
+            locals: vec![None; resolution.names.len()],
+            loans: Vec::new(),
+            point: 0,
+            type_errors: Vec::new(),
+            borrow_errors: Vec::new(),
+            steps: Vec::new(),
+        };
+        checker.function();
+
+        type_errors.extend(checker.type_errors);
+        borrow_errors.extend(checker.borrow_errors);
+        report.steps.extend(checker.steps);
+    }
+
+    report.diagnostics = if type_errors.is_empty() {
+        borrow_errors
+    } else {
+        type_errors
+    };
+    report
+}
+
+fn line(program: &Program, span: Span) -> usize {
+    position(&program.source, span.start).0
+}
+
+// A function returning a reference has to say what it borrows from. With
+// exactly one reference parameter the compiler can assume it is that one
+fn signature(program: &Program, function: &Function, report: &mut Report) {
+    let (ret, span) = match &function.ret {
+        Some((ret, span)) if ret.is_ref() => (ret, *span),
+        _ => return,
+    };
+
+    let references = function.params.iter().filter(|p| p.ty.is_ref()).count();
+    if references == 1 {
+        report.steps.push(format!(
+            "line {}: `{}` returns `{}`, which can only borrow from its one reference parameter",
+            line(program, span),
+            function.name,
+            ret
+        ));
+        return;
+    }
+
+    let help = if references == 0 {
+        "this function's return type contains a borrowed value, but there is no value for it to be borrowed from"
+    } else {
+        "this function's return type contains a borrowed value, but the signature does not say which one of its parameters it is borrowed from"
+    };
+
+    report.steps.push(format!(
+        "line {}: `{}` returns a reference but takes {} references, so nothing says what it borrows from -> E0106",
+        line(program, span),
+        function.name,
+        references
+    ));
+    report.diagnostics.push(
+        Diagnostic::error(
+            Some("E0106"),
+            "missing lifetime specifier",
+            Span {
+                start: span.start,
+                end: span.start + 1,
+            },
+            "expected named lifetime parameter",
+        )
+        .help(help),
+    );
+}
+
+// Which declaration every variable in a function refers to, and every
+// point at which each declaration is used
+struct Resolution {
+    names: Vec<String>,
+    // Declarations and uses, keyed by where their name starts
+    decls: HashMap<usize, usize>,
+    uses: HashMap<usize, usize>,
+    // Points at which each local is used, with the span of each use
+    points: Vec<Vec<(usize, Span)>>,
+}
+
+fn resolve(program: &Program, function: &Function, errors: &mut Vec<Diagnostic>) -> Resolution {
+    let mut resolver = Resolver {
+        program,
+        resolution: Resolution {
+            names: Vec::new(),
+            decls: HashMap::new(),
+            uses: HashMap::new(),
+            points: Vec::new(),
+        },
+        scopes: vec![Vec::new()],
+        point: 0,
+        errors,
+    };
+
+    for param in &function.params {
+        resolver.declare(&param.name, param.span);
+    }
+    resolver.block(&function.body);
+
+    resolver.resolution
+}
+
+struct Resolver<'a> {
+    program: &'a Program,
+    resolution: Resolution,
+    scopes: Vec<Vec<(String, usize)>>,
+    point: usize,
+    errors: &'a mut Vec<Diagnostic>,
+}
+
+impl Resolver<'_> {
+    fn declare(&mut self, name: &str, span: Span) {
+        let local = self.resolution.names.len();
+        self.resolution.names.push(name.to_string());
+        self.resolution.points.push(Vec::new());
+        self.resolution.decls.insert(span.start, local);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), local));
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(Vec::new());
+
+        for stmt in &block.stmts {
+            match stmt {
+                Stmt::Let {
+                    name,
+                    name_span,
+                    init,
+                    ..
+                } => {
+                    self.point += 1;
+                    self.expr(init);
+                    self.declare(name, *name_span);
+                }
+                Stmt::Expr(expr) => {
+                    self.point += 1;
+                    self.expr(expr);
+                }
+                Stmt::Block(block) => self.block(block),
+            }
+        }
+        if let Some(tail) = &block.tail {
+            self.point += 1;
+            self.expr(tail);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Var(name) => {
+                let local = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .flat_map(|scope| scope.iter().rev())
+                    .find(|(n, _)| n == name)
+                    .map(|&(_, local)| local);
+
+                match local {
+                    Some(local) => {
+                        self.resolution.uses.insert(expr.span.start, local);
+                        self.resolution.points[local].push((self.point, expr.span));
+                    }
+                    None => self.errors.push(Diagnostic::error(
+                        Some("E0425"),
+                        &format!("cannot find value `{}` in this scope", name),
+                        expr.span,
+                        "not found in this scope",
+                    )),
+                }
+            }
+            ExprKind::Ref(_, inner) => self.expr(inner),
+            ExprKind::Call(name, args) => {
+                if self.program.function(name).is_none() {
+                    self.errors.push(Diagnostic::error(
+                        Some("E0425"),
+                        &format!("cannot find function `{}` in this scope", name),
+                        Span {
+                            start: expr.span.start,
+                            end: expr.span.start + name.len(),
+                        },
+                        "not found in this scope",
+                    ));
+                }
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Method(receiver, _, args) => {
+                self.expr(receiver);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Println(_, args) => args.iter().for_each(|arg| self.expr(arg)),
+            ExprKind::StringFrom(_) | ExprKind::Str(_) | ExprKind::Int(_) => {}
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Local {
+    name: String,
+    ty: Type,
+    mutable: bool,
+    moved: Option<Span>,
+    // The written type of a parameter, which a help message can point at
+    ty_span: Option<Span>,
+    param: bool,
+}
+
+// A borrow of a local, held by the references it was stored in
+struct Loan {
+    place: usize,
+    mutable: bool,
+    span: Span,
+    point: usize,
+    holders: Vec<usize>,
+}
+
+// How an expression's value is used
+#[derive(Clone, Copy, PartialEq)]
+enum Use {
+    // Moved or copied somewhere
+    Value,
+    // Only read through a shared borrow, as `println!` does
+    Read,
+}
+
+struct Checker<'a> {
+    program: &'a Program,
+    function: &'a Function,
+    resolution: &'a Resolution,
+    locals: Vec<Option<Local>>,
+    loans: Vec<Loan>,
+    point: usize,
+    type_errors: Vec<Diagnostic>,
+    borrow_errors: Vec<Diagnostic>,
+    steps: Vec<String>,
+}
+
+impl Checker<'_> {
+    fn line(&self, span: Span) -> usize {
+        line(self.program, span)
+    }
+
+    fn step(&mut self, span: Span, text: String) {
+        let line = self.line(span);
+        self.steps.push(format!("line {}: {}", line, text));
+    }
+
+    fn local(&self, id: usize) -> &Local {
+        self.locals[id]
+            .as_ref()
+            .expect("locals are declared before they are used")
+    }
+
+    fn function(&mut self) {
+        let function = self.function;
+
+        for (id, param) in function.params.iter().enumerate() {
+            self.locals[id] = Some(Local {
+                name: param.name.clone(),
+                ty: param.ty.clone(),
+                mutable: false,
+                moved: None,
+                ty_span: Some(param.ty_span),
+                param: true,
+            });
+        }
+
+        let (ty, loans) = self.block(&function.body);
+        let expected = function
+            .ret
+            .as_ref()
+            .map_or(Type::Unit, |(ty, _)| ty.clone());
+
+        let tail = match &function.body.tail {
+            Some(tail) => tail,
+            None => {
+                if expected != Type::Unit {
+                    let span = function.ret.as_ref().map_or(function.name_span, |r| r.1);
+                    self.type_errors.push(Diagnostic::error(
+                        Some("E0308"),
+                        "mismatched types",
+                        span,
+                        &format!("expected `{}`, found `()`", expected),
+                    ));
+                }
+                return;
+            }
+        };
+
+        if ty != expected {
+            self.type_errors.push(Diagnostic::error(
+                Some("E0308"),
+                "mismatched types",
+                tail.span,
+                &format!("expected `{}`, found `{}`", expected, ty),
+            ));
+            return;
+        }
+
+        // A returned reference may only borrow from what was passed in
+        for loan in loans {
+            let place = self.local(self.loans[loan].place).clone();
+            if !place.param {
+                self.step(
+                    tail.span,
+                    format!(
+                        "the returned reference borrows `{}`, which is dropped when `{}` returns -> E0515",
+                        place.name, function.name
+                    ),
+                );
+                self.borrow_errors.push(Diagnostic::error(
+                    Some("E0515"),
+                    &format!("cannot return reference to local variable `{}`", place.name),
+                    tail.span,
+                    "returns a reference to data owned by the current function",
+                ));
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) -> (Type, Vec<usize>) {
+        for stmt in &block.stmts {
+            match stmt {
+                Stmt::Let {
+                    name,
+                    mutable,
+                    name_span,
+                    init,
+                    ..
+                } => {
+                    self.point += 1;
+                    let (ty, loans) = self.expr(init, Use::Value);
+                    let id = self.resolution.decls[&name_span.start];
+
+                    self.locals[id] = Some(Local {
+                        name: name.clone(),
+                        ty: ty.clone(),
+                        mutable: *mutable,
+                        moved: None,
+                        ty_span: None,
+                        param: false,
+                    });
+
+                    if let ExprKind::StringFrom(text) = &init.kind {
+                        self.step(
+                            init.span,
+                            format!("`{}` owns a new `String` {:?}", name, text),
+                        );
+                    }
+                    for loan in loans {
+                        self.loans[loan].holders.push(id);
+                        self.describe_loan(loan, name);
+                    }
+                }
+                Stmt::Expr(expr) => {
+                    self.point += 1;
+                    self.expr(expr, Use::Value);
+                }
+                Stmt::Block(inner) => {
+                    self.block(inner);
+                    continue;
+                }
+            }
+            self.ended_borrows(stmt.span());
+        }
+
+        match &block.tail {
+            Some(tail) => {
+                self.point += 1;
+                self.expr(tail, Use::Value)
+            }
+            None => (Type::Unit, Vec::new()),
+        }
+    }
+
+    fn describe_loan(&mut self, loan: usize, holder: &str) {
+        let last = self.resolution.points[*self.loans[loan].holders.last().unwrap_or(&0)]
+            .iter()
+            .map(|&(_, span)| span)
+            .next_back();
+        let place = self.local(self.loans[loan].place).name.clone();
+        let kind = if self.loans[loan].mutable {
+            "mutably"
+        } else {
+            "immutably"
+        };
+        let until = match last {
+            Some(span) => format!("until its last use on line {}", self.line(span)),
+            None => String::from("but is never used, so the borrow ends right away"),
+        };
+
+        let span = self.loans[loan].span;
+        self.step(
+            span,
+            format!("`{}` borrows `{}` {}, {}", holder, place, kind, until),
+        );
+    }
+
+    // Notes the references whose last use was the statement just checked
+    fn ended_borrows(&mut self, span: Span) {
+        let point = self.point;
+        let mut ended = Vec::new();
+
+        for loan in &self.loans {
+            let last = loan
+                .holders
+                .iter()
+                .filter_map(|&h| self.resolution.points[h].iter().map(|&(p, _)| p).max())
+                .max();
+
+            if last == Some(point) {
+                ended.push((loan.holders.clone(), loan.place));
+            }
+        }
+
+        for (holders, place) in ended {
+            let holders: Vec<String> = holders
+                .iter()
+                .map(|&h| format!("`{}`", self.local(h).name))
+                .collect();
+            let place = self.local(place).name.clone();
+            self.step(
+                span,
+                format!(
+                    "{} is not used again, so its borrow of `{}` ends here",
+                    holders.join(" and "),
+                    place
+                ),
+            );
+        }
+    }
+
+    // Whether `loan` is still needed at the current point
+    fn is_live(&self, loan: &Loan) -> bool {
+        if loan.holders.is_empty() {
+            return loan.point == self.point;
+        }
+
+        loan.holders.iter().any(|&h| {
+            self.resolution.points[h]
+                .iter()
+                .any(|&(p, _)| p > self.point)
+        })
+    }
+
+    // Where a live loan is next used, for "later used here" labels
+    fn later_use(&self, loan: &Loan) -> Option<Span> {
+        loan.holders
+            .iter()
+            .flat_map(|&h| self.resolution.points[h].iter())
+            .filter(|&&(p, _)| p > self.point)
+            .min_by_key(|&&(p, span)| (p, span.start))
+            .map(|&(_, span)| span)
+    }
+
+    // The first live loan of `place`, preferring mutable ones
+    fn conflicting(&self, place: usize, mutable: bool) -> Option<&Loan> {
+        let live = self
+            .loans
+            .iter()
+            .filter(|loan| loan.place == place && self.is_live(loan));
+
+        if mutable {
+            live.min_by_key(|loan| !loan.mutable)
+        } else {
+            live.filter(|loan| loan.mutable)
+                .min_by_key(|loan| loan.point)
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, how: Use) -> (Type, Vec<usize>) {
+        match &expr.kind {
+            ExprKind::StringFrom(_) => (Type::String, Vec::new()),
+            ExprKind::Str(_) => (Type::Str, Vec::new()),
+            ExprKind::Int(_) => (Type::Usize, Vec::new()),
+            ExprKind::Var(_) => self.var(expr, how),
+            ExprKind::Ref(mutable, inner) => self.borrow(expr, *mutable, inner),
+            ExprKind::Call(name, args) => self.call(expr, name, args),
+            ExprKind::Method(receiver, method, args) => self.method(expr, receiver, method, args),
+            ExprKind::Println(format, args) => {
+                let placeholders = format.matches("{}").count();
+                if placeholders != args.len() {
+                    self.type_errors.push(Diagnostic::error(
+                        None,
+                        &format!(
+                            "{} positional argument{} in format string, but there {}",
+                            placeholders,
+                            if placeholders == 1 { "" } else { "s" },
+                            match args.len() {
+                                1 => String::from("is 1 argument"),
+                                n => format!("are {} arguments", n),
+                            }
+                        ),
+                        expr.span,
+                        "",
+                    ));
+                }
+
+                // `println!` only ever borrows its arguments
+                for arg in args {
+                    self.expr(arg, Use::Read);
+                }
+                (Type::Unit, Vec::new())
+            }
+        }
+    }
+
+    fn var(&mut self, expr: &Expr, how: Use) -> (Type, Vec<usize>) {
+        let id = self.resolution.uses[&expr.span.start];
+        let local = self.local(id).clone();
+
+        // A reference passes on whatever it is borrowing
+        let held: Vec<usize> = (0..self.loans.len())
+            .filter(|&l| self.loans[l].holders.contains(&id))
+            .collect();
+
+        if local.ty.is_ref() {
+            return (local.ty, held);
+        }
+
+        if let Some(moved) = local.moved {
+            let (message, label) = match how {
+                Use::Value => ("use of moved value", "value used here after move"),
+                Use::Read => ("borrow of moved value", "value borrowed here after move"),
+            };
+            self.step(
+                expr.span,
+                format!(
+                    "`{}` was moved away on line {} and no longer owns anything -> E0382",
+                    local.name,
+                    self.line(moved)
+                ),
+            );
+            self.borrow_errors.push(
+                Diagnostic::error(
+                    Some("E0382"),
+                    &format!("{}: `{}`", message, local.name),
+                    expr.span,
+                    label,
+                )
+                .label(moved, "value moved here"),
+            );
+            return (local.ty, Vec::new());
+        }
+
+        match how {
+            Use::Read => self.access(expr.span, id, false),
+            Use::Value if !local.ty.is_copy() => {
+                if let Some(loan) = self.conflicting(id, true) {
+                    let (borrow, later) = (loan.span, self.later_use(loan));
+                    self.step(
+                        expr.span,
+                        format!(
+                            "`{}` cannot be moved while the borrow from line {} is still in use -> E0505",
+                            local.name,
+                            self.line(borrow)
+                        ),
+                    );
+
+                    let mut error = Diagnostic::error(
+                        Some("E0505"),
+                        &format!("cannot move out of `{}` because it is borrowed", local.name),
+                        expr.span,
+                        &format!("move out of `{}` occurs here", local.name),
+                    )
+                    .label(borrow, &format!("borrow of `{}` occurs here", local.name));
+                    if let Some(later) = later {
+                        error = error.label(later, "borrow later used here");
+                    }
+                    self.borrow_errors.push(error);
+                }
+
+                self.step(expr.span, format!("`{}` is moved here", local.name));
+                if let Some(local) = self.locals[id].as_mut() {
+                    local.moved = Some(expr.span);
+                }
+            }
+            Use::Value => {}
+        }
+
+        (local.ty, Vec::new())
+    }
+
+    // Checks that `id` can be used through a new borrow at `span`, reporting
+    // the first live borrow that gets in the way
+    fn access(&mut self, span: Span, id: usize, mutable: bool) {
+        let name = self.local(id).name.clone();
+        let conflict = self
+            .conflicting(id, mutable)
+            .map(|loan| (loan.span, loan.mutable, self.later_use(loan)));
+
+        let (borrow, borrow_mutable, later) = match conflict {
+            Some(conflict) => conflict,
+            None => return,
+        };
+
+        let (code, message, first, second, later_text) = match (mutable, borrow_mutable) {
+            (true, true) => (
+                "E0499",
+                format!(
+                    "cannot borrow `{}` as mutable more than once at a time",
+                    name
+                ),
+                "first mutable borrow occurs here",
+                "second mutable borrow occurs here",
+                "first borrow later used here",
+            ),
+            (true, false) => (
+                "E0502",
+                format!(
+                    "cannot borrow `{}` as mutable because it is also borrowed as immutable",
+                    name
+                ),
+                "immutable borrow occurs here",
+                "mutable borrow occurs here",
+                "immutable borrow later used here",
+            ),
+            _ => (
+                "E0502",
+                format!(
+                    "cannot borrow `{}` as immutable because it is also borrowed as mutable",
+                    name
+                ),
+                "mutable borrow occurs here",
+                "immutable borrow occurs here",
+                "mutable borrow later used here",
+            ),
+        };
+
+        let later_line = later.map_or(String::new(), |l| {
+            format!(", used again on line {}", self.line(l))
+        });
+        self.step(
+            span,
+            format!(
+                "`{}` is still borrowed{} from line {}{} -> {}",
+                name,
+                if borrow_mutable { " mutably" } else { "" },
+                self.line(borrow),
+                later_line,
+                code
+            ),
+        );
+
+        let mut error = Diagnostic::error(Some(code), &message, span, second).label(borrow, first);
+        if let Some(later) = later {
+            error = error.label(later, later_text);
+        }
+        self.borrow_errors.push(error);
+    }
+
+    fn borrow(&mut self, expr: &Expr, mutable: bool, inner: &Expr) -> (Type, Vec<usize>) {
+        let id = match &inner.kind {
+            ExprKind::Var(_) => self.resolution.uses[&inner.span.start],
+            // Borrowing a temporary, such as `&String::from("Hello")`
+            _ => {
+                let (ty, loans) = self.expr(inner, Use::Value);
+                return (Type::Ref(mutable, Box::new(ty)), loans);
+            }
+        };
+
+        let local = self.local(id).clone();
+        let ty = Type::Ref(mutable, Box::new(local.ty.clone()));
+
+        if let Some(moved) = local.moved {
+            self.borrow_errors.push(
+                Diagnostic::error(
+                    Some("E0382"),
+                    &format!("borrow of moved value: `{}`", local.name),
+                    expr.span,
+                    "value borrowed here after move",
+                )
+                .label(moved, "value moved here"),
+            );
+            return (ty, Vec::new());
+        }
+
+        if mutable && !local.mutable && !local.ty.is_ref() {
+            self.step(
+                expr.span,
+                format!(
+                    "`{}` was not declared with `mut`, so it cannot be borrowed mutably -> E0596",
+                    local.name
+                ),
+            );
+            self.borrow_errors.push(
+                Diagnostic::error(
+                    Some("E0596"),
+                    &format!(
+                        "cannot borrow `{}` as mutable, as it is not declared as mutable",
+                        local.name
+                    ),
+                    expr.span,
+                    "cannot borrow as mutable",
+                )
+                .help(&format!(
+                    "consider changing this to be mutable: `mut {}`",
+                    local.name
+                )),
+            );
+            return (ty, Vec::new());
+        }
+
+        self.access(expr.span, id, mutable);
+
+        self.loans.push(Loan {
+            place: id,
+            mutable,
+            span: expr.span,
+            point: self.point,
+            holders: Vec::new(),
+        });
+
+        // Borrowing a reference keeps what it borrows borrowed as well
+        let mut loans = vec![self.loans.len() - 1];
+        if local.ty.is_ref() {
+            loans.extend((0..self.loans.len()).filter(|&l| self.loans[l].holders.contains(&id)));
+        }
+
+        (ty, loans)
+    }
+
+    fn call(&mut self, expr: &Expr, name: &str, args: &[Expr]) -> (Type, Vec<usize>) {
+        let function = match self.program.function(name) {
+            Some(function) => function,
+            None => return (Type::Unit, Vec::new()),
+        };
+        let ret = function
+            .ret
+            .as_ref()
+            .map_or(Type::Unit, |(ty, _)| ty.clone());
+
+        if function.params.len() != args.len() {
+            self.type_errors.push(Diagnostic::error(
+                Some("E0061"),
+                &format!(
+                    "this function takes {} argument{} but {} {} supplied",
+                    function.params.len(),
+                    if function.params.len() == 1 { "" } else { "s" },
+                    args.len(),
+                    if args.len() == 1 {
+                        "argument was"
+                    } else {
+                        "arguments were"
+                    }
+                ),
+                expr.span,
+                "",
+            ));
+        }
+
+        let mut flowing = Vec::new();
+        for (arg, param) in args.iter().zip(&function.params) {
+            let (ty, loans) = self.expr(arg, Use::Value);
+
+            if ty != param.ty {
+                self.type_errors.push(
+                    Diagnostic::error(
+                        Some("E0308"),
+                        "mismatched types",
+                        arg.span,
+                        &format!("expected `{}`, found `{}`", param.ty, ty),
+                    )
+                    .label(param.ty_span, "parameter declared here"),
+                );
+            }
+
+            if !loans.is_empty() {
+                self.step(
+                    arg.span,
+                    format!(
+                        "`{}` is lent to `{}` as `{}` for the length of the call",
+                        arg_text(&self.program.source, arg.span),
+                        param.name,
+                        param.ty
+                    ),
+                );
+            }
+            flowing.extend(loans);
+        }
+
+        // A returned reference borrows from the reference passed in
+        if ret.is_ref() {
+            (ret, flowing)
+        } else {
+            (ret, Vec::new())
+        }
+    }
+
+    fn method(
+        &mut self,
+        expr: &Expr,
+        receiver: &Expr,
+        method: &str,
+        args: &[Expr],
+    ) -> (Type, Vec<usize>) {
+        for arg in args {
+            self.expr(arg, Use::Value);
+        }
+
+        let id = match &receiver.kind {
+            ExprKind::Var(_) => Some(self.resolution.uses[&receiver.span.start]),
+            _ => None,
+        };
+        let ty = match id {
+            Some(id) => self.local(id).ty.clone(),
+            None => self.expr(receiver, Use::Value).0,
+        };
+        let target = match &ty {
+            Type::Ref(_, inner) => (**inner).clone(),
+            other => other.clone(),
+        };
+
+        let (mutates, ret) = match (method, &target) {
+            ("len", Type::String) => (false, Type::Usize),
+            ("push_str", Type::String) => (true, Type::Unit),
+            _ => {
+                self.type_errors.push(Diagnostic::error(
+                    Some("E0599"),
+                    &format!(
+                        "no method named `{}` found for `{}` in this scope",
+                        method, ty
+                    ),
+                    expr.span,
+                    "method not found",
+                ));
+                return (Type::Unit, Vec::new());
+            }
+        };
+
+        let id = match id {
+            Some(id) => id,
+            None => return (ret, Vec::new()),
+        };
+        let local = self.local(id).clone();
+
+        match (&local.ty, mutates) {
+            // Reading through a reference only needs the reference
+            (Type::Ref(..), false) | (Type::Ref(true, _), true) => {}
+            (Type::Ref(false, _), true) => {
+                self.step(
+                    receiver.span,
+                    format!(
+                        "`{}` is a `&` reference, so the `String` behind it cannot be changed -> E0596",
+                        local.name
+                    ),
+                );
+
+                let mut error = Diagnostic::error(
+                    Some("E0596"),
+                    &format!(
+                        "cannot borrow `*{}` as mutable, as it is behind a `&` reference",
+                        local.name
+                    ),
+                    receiver.span,
+                    &format!(
+                        "`{}` is a `&` reference, so the data it refers to cannot be borrowed as mutable",
+                        local.name
+                    ),
+                );
+                if let Some(ty_span) = local.ty_span {
+                    error = error.label(
+                        ty_span,
+                        "help: consider changing this to be a mutable reference: `&mut String`",
+                    );
+                }
+                self.borrow_errors.push(error);
+            }
+            // Calling a method on an owned `String` borrows it for the call
+            (_, _) => {
+                if let Some(moved) = local.moved {
+                    self.borrow_errors.push(
+                        Diagnostic::error(
+                            Some("E0382"),
+                            &format!("borrow of moved value: `{}`", local.name),
+                            receiver.span,
+                            "value borrowed here after move",
+                        )
+                        .label(moved, "value moved here"),
+                    );
+                } else if mutates && !local.mutable {
+                    self.step(
+                        receiver.span,
+                        format!(
+                            "`{}` was not declared with `mut`, so it cannot be changed -> E0596",
+                            local.name
+                        ),
+                    );
+                    self.borrow_errors.push(
+                        Diagnostic::error(
+                            Some("E0596"),
+                            &format!(
+                                "cannot borrow `{}` as mutable, as it is not declared as mutable",
+                                local.name
+                            ),
+                            receiver.span,
+                            "cannot borrow as mutable",
+                        )
+                        .help(&format!(
+                            "consider changing this to be mutable: `mut {}`",
+                            local.name
+                        )),
+                    );
+                } else {
+                    self.access(receiver.span, id, mutates);
+                }
+            }
+        }
+
+        (ret, Vec::new())
+    }
+}
+
+fn arg_text(source: &str, span: Span) -> &str {
+    &source[span.start..span.end]
+}
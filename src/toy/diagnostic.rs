@@ -0,0 +1,147 @@
+// Project: references
+// Author: Greg Folker
+
+// Errors in toy programs, printed the way `rustc` prints its own
+
+use super::ast::Span;
+
+pub struct Label {
+    pub span: Span,
+    pub text: String,
+    /// The label the error is about, underlined with `^` rather than `-`
+    pub primary: bool,
+}
+
+pub struct Diagnostic {
+    /// The `rustc` error code this error corresponds to, if any
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    /// Lines printed after the snippet, such as `help: ...`
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: Option<&'static str>, message: &str, span: Span, label: &str) -> Diagnostic {
+        Diagnostic {
+            code,
+            message: message.to_string(),
+            labels: vec![Label {
+                span,
+                text: label.to_string(),
+                primary: true,
+            }],
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn label(mut self, span: Span, text: &str) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            text: text.to_string(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn help(mut self, text: &str) -> Diagnostic {
+        self.notes.push(format!("help: {}", text));
+        self
+    }
+
+    pub fn note(mut self, text: &str) -> Diagnostic {
+        self.notes.push(format!("note: {}", text));
+        self
+    }
+
+    pub fn primary_span(&self) -> Span {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .map_or(Span { start: 0, end: 0 }, |label| label.span)
+    }
+
+    /// Renders the error against `source`, the contents of `file`
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let mut out = match self.code {
+            Some(code) => format!("error[{}]: {}\n", code, self.message),
+            None => format!("error: {}\n", self.message),
+        };
+
+        let (line, column) = position(source, self.primary_span().start);
+        let lines: Vec<&str> = source.lines().collect();
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|label| (label.span.start, !label.primary));
+
+        let last_line = labels
+            .iter()
+            .map(|label| position(source, label.span.start).0)
+            .max()
+            .unwrap_or(line);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        out.push_str(&format!("{}--> {}:{}:{}\n", gutter, file, line, column));
+        out.push_str(&format!("{} |\n", gutter));
+
+        let mut previous: Option<usize> = None;
+        for label in labels {
+            let (line, column) = position(source, label.span.start);
+            let text = lines.get(line - 1).copied().unwrap_or("");
+
+            if previous != Some(line) {
+                match previous {
+                    Some(p) if line == p + 2 => {
+                        let between = lines.get(p).copied().unwrap_or("");
+                        let between = format!("{:>width$} | {}", p + 1, between, width = width);
+                        out.push_str(between.trim_end());
+                        out.push('\n');
+                    }
+                    Some(p) if line > p + 2 => out.push_str("...\n"),
+                    _ => {}
+                }
+                out.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+            }
+            previous = Some(line);
+
+            // Spans running over more than one line are underlined to the
+            // end of their first line
+            let end = label
+                .span
+                .end
+                .min(label.span.start + text.len() + 1 - column);
+            let marker = if label.primary { "^" } else { "-" };
+            let underline = marker.repeat((end - label.span.start).max(1));
+            out.push_str(
+                format!(
+                    "{} | {}{} {}",
+                    gutter,
+                    " ".repeat(column - 1),
+                    underline,
+                    label.text
+                )
+                .trim_end(),
+            );
+            out.push('\n');
+        }
+
+        if !self.notes.is_empty() {
+            out.push_str(&format!("{} |\n", gutter));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = {}\n", gutter, note));
+        }
+
+        out
+    }
+}
+
+/// The line and column, both counting from 1, of the byte at `offset`
+pub fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+    (line, column)
+}
@@ -0,0 +1,327 @@
+// Project: references
+// Author: Greg Folker
+
+// Runs toy programs that have passed the checker. Every `String` lives in
+// a heap of its own, so moves, borrows and drops act on real cells that
+// can be inspected while the program runs
+//
+// The checker does not look at recursion, so a function that calls itself
+// forever passes it. Calls are limited to `MAX_DEPTH` deep, and a program
+// that goes deeper is stopped with an error instead of overflowing the
+// stack of the tool itself
+
+use super::ast::{Block, Expr, ExprKind, Stmt};
+use super::Program;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// An owned `String`, by the heap cell holding its contents
+    Str(usize),
+    /// A reference to the `String` in a heap cell
+    Ref(usize, bool),
+    Usize(usize),
+    /// A string literal
+    Text(String),
+    Unit,
+}
+
+/// A variable in a stack frame
+#[derive(Clone)]
+pub struct Slot {
+    pub name: String,
+    /// `None` once the value has been moved out
+    pub value: Option<Value>,
+}
+
+pub struct Frame {
+    pub function: String,
+    pub slots: Vec<Slot>,
+}
+
+/// The contents of a `String`, and whether it has been dropped
+pub struct Cell {
+    pub text: String,
+    pub freed: bool,
+}
+
+/// Something the program did that the lessons are interested in
+#[derive(Clone, Debug)]
+pub enum Event {
+    Print(String),
+    /// A `String` owned by `name` went out of scope and was freed
+    Drop {
+        name: String,
+        text: String,
+    },
+}
+
+/// How many calls deep a program may go before it is stopped
+pub const MAX_DEPTH: usize = 200;
+
+pub struct Machine<'p> {
+    program: &'p Program,
+    pub frames: Vec<Frame>,
+    pub heap: Vec<Cell>,
+    pub events: Vec<Event>,
+    /// Why the program was stopped, after which nothing more runs
+    pub error: Option<String>,
+}
+
+// How an expression's value is used, which decides whether a `String`
+// variable is moved out of
+#[derive(Clone, Copy, PartialEq)]
+enum Use {
+    Value,
+    Read,
+}
+
+impl<'p> Machine<'p> {
+    pub fn new(program: &'p Program) -> Machine<'p> {
+        Machine {
+            program,
+            frames: Vec::new(),
+            heap: Vec::new(),
+            events: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Runs `main` to completion and returns everything it printed
+    pub fn run(&mut self) -> Result<String, String> {
+        let main = self
+            .program
+            .function("main")
+            .ok_or_else(|| String::from("`main` function not found"))?;
+
+        self.frames.push(Frame {
+            function: main.name.clone(),
+            slots: Vec::new(),
+        });
+        self.block(&main.body);
+        self.frames.pop();
+
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.output()),
+        }
+    }
+
+    /// Everything printed so far
+    pub fn output(&self) -> String {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Print(line) => Some(format!("{}\n", line)),
+                Event::Drop { .. } => None,
+            })
+            .collect()
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("a frame is pushed before any code runs")
+    }
+
+    fn slot(&mut self, name: &str) -> &mut Slot {
+        self.frame()
+            .slots
+            .iter_mut()
+            .rev()
+            .find(|slot| slot.name == name)
+            .expect("the checker resolved every variable")
+    }
+
+    fn block(&mut self, block: &Block) -> Value {
+        let depth = self.frame().slots.len();
+
+        for stmt in &block.stmts {
+            if self.error.is_some() {
+                break;
+            }
+            self.stmt(stmt);
+        }
+        let value = match &block.tail {
+            Some(tail) if self.error.is_none() => self.expr(tail, Use::Value),
+            _ => Value::Unit,
+        };
+
+        self.drop_slots(depth);
+        value
+    }
+
+    pub(crate) fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, init, .. } => {
+                let value = self.expr(init, Use::Value);
+                self.frame().slots.push(Slot {
+                    name: name.clone(),
+                    value: Some(value),
+                });
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr, Use::Value);
+            }
+            Stmt::Block(block) => {
+                self.block(block);
+            }
+        }
+    }
+
+    /// Evaluates the value at the end of a block
+    pub(crate) fn tail(&mut self, expr: &Expr) -> Value {
+        self.expr(expr, Use::Value)
+    }
+
+    // Drops every variable declared after the first `depth`, newest first
+    pub(crate) fn drop_slots(&mut self, depth: usize) {
+        while self.frame().slots.len() > depth {
+            let slot = match self.frame().slots.pop() {
+                Some(slot) => slot,
+                None => break,
+            };
+
+            if let Some(Value::Str(cell)) = slot.value {
+                self.heap[cell].freed = true;
+                self.events.push(Event::Drop {
+                    name: slot.name,
+                    text: self.heap[cell].text.clone(),
+                });
+            }
+        }
+    }
+
+    fn alloc(&mut self, text: &str) -> usize {
+        self.heap.push(Cell {
+            text: text.to_string(),
+            freed: false,
+        });
+        self.heap.len() - 1
+    }
+
+    // The heap cell a `String` or a reference to one points at
+    fn cell(value: &Value) -> Option<usize> {
+        match value {
+            Value::Str(cell) | Value::Ref(cell, _) => Some(*cell),
+            _ => None,
+        }
+    }
+
+    fn display(&self, value: &Value) -> String {
+        match value {
+            Value::Str(cell) | Value::Ref(cell, _) => self.heap[*cell].text.clone(),
+            Value::Usize(n) => n.to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Unit => String::from("()"),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, how: Use) -> Value {
+        match &expr.kind {
+            ExprKind::StringFrom(text) => Value::Str(self.alloc(text)),
+            ExprKind::Str(text) => Value::Text(text.clone()),
+            ExprKind::Int(n) => Value::Usize(*n),
+            ExprKind::Var(name) => {
+                let slot = self.slot(name);
+                match (&slot.value, how) {
+                    // Using a `String` by value moves it out of the variable
+                    (Some(Value::Str(_)), Use::Value) => slot.value.take(),
+                    (value, _) => value.clone(),
+                }
+                .unwrap_or(Value::Unit)
+            }
+            ExprKind::Ref(mutable, inner) => {
+                let value = match &inner.kind {
+                    ExprKind::Var(name) => self.slot(name).value.clone(),
+                    _ => {
+                        // A borrowed temporary lives until the end of the
+                        // enclosing block, as if it were a hidden variable
+                        let value = self.expr(inner, Use::Value);
+                        self.frame().slots.push(Slot {
+                            name: String::from("<temporary>"),
+                            value: Some(value.clone()),
+                        });
+                        Some(value)
+                    }
+                };
+
+                match value.as_ref().and_then(Machine::cell) {
+                    Some(cell) => Value::Ref(cell, *mutable),
+                    None => value.unwrap_or(Value::Unit),
+                }
+            }
+            ExprKind::Call(name, args) => {
+                let args: Vec<Value> = args.iter().map(|arg| self.expr(arg, Use::Value)).collect();
+                self.call(name, args)
+            }
+            ExprKind::Method(receiver, method, args) => {
+                let args: Vec<Value> = args.iter().map(|arg| self.expr(arg, Use::Value)).collect();
+                let receiver = self.expr(receiver, Use::Read);
+                let cell = match Machine::cell(&receiver) {
+                    Some(cell) => cell,
+                    None => return Value::Unit,
+                };
+
+                match method.as_str() {
+                    "push_str" => {
+                        let text: String = args.iter().map(|arg| self.display(arg)).collect();
+                        self.heap[cell].text.push_str(&text);
+                        Value::Unit
+                    }
+                    "len" => Value::Usize(self.heap[cell].text.len()),
+                    _ => Value::Unit,
+                }
+            }
+            ExprKind::Println(format, args) => {
+                let args: Vec<Value> = args.iter().map(|arg| self.expr(arg, Use::Read)).collect();
+                let mut pieces = format.split("{}");
+                let mut line = pieces.next().unwrap_or("").to_string();
+
+                for (piece, arg) in pieces.zip(&args) {
+                    line.push_str(&self.display(arg));
+                    line.push_str(piece);
+                }
+
+                self.events.push(Event::Print(line));
+                Value::Unit
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Value {
+        let function = match self.program.function(name) {
+            Some(function) => function,
+            None => return Value::Unit,
+        };
+        if self.frames.len() >= MAX_DEPTH {
+            self.error = Some(format!(
+                "recursion limit reached: `{}` was called more than {} calls deep, \
+                 does it call itself forever?",
+                name, MAX_DEPTH
+            ));
+            return Value::Unit;
+        }
+
+        let slots = function
+            .params
+            .iter()
+            .zip(args)
+            .map(|(param, value)| Slot {
+                name: param.name.clone(),
+                value: Some(value),
+            })
+            .collect();
+
+        self.frames.push(Frame {
+            function: function.name.clone(),
+            slots,
+        });
+        let value = self.block(&function.body);
+
+        // Parameters go out of scope last, once the body is done
+        self.drop_slots(0);
+        self.frames.pop();
+
+        value
+    }
+}
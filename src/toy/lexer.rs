@@ -0,0 +1,94 @@
+// Project: references
+// Author: Greg Folker
+
+use super::ast::Span;
+use super::diagnostic::Diagnostic;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Ident(String),
+    Str(String),
+    Int(usize),
+    /// Punctuation such as `(`, `&`, `->` or `::`
+    Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "->", "::", "(", ")", "{", "}", ",", ";", ":", "&", "=", ".", "!",
+];
+
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, Diagnostic> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        let c = rest.chars().next().unwrap_or(' ');
+
+        if c.is_whitespace() {
+            i += c.len_utf8();
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if c == '"' {
+            let (text, len) = string(rest).ok_or_else(|| {
+                Diagnostic::error(
+                    None,
+                    "unterminated double quote string",
+                    span(i, source.len()),
+                    "",
+                )
+            })?;
+            tokens.push((Token::Str(text), span(i, i + len)));
+            i += len;
+        } else if c.is_ascii_digit() {
+            let len = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let n = rest[..len].parse().unwrap_or(usize::MAX);
+            tokens.push((Token::Int(n), span(i, i + len)));
+            i += len;
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.len()
+                - rest
+                    .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_')
+                    .len();
+            tokens.push((Token::Ident(rest[..len].to_string()), span(i, i + len)));
+            i += len;
+        } else if let Some(p) = PUNCTUATION.iter().find(|p| rest.starts_with(**p)) {
+            tokens.push((Token::Punct(p), span(i, i + p.len())));
+            i += p.len();
+        } else {
+            return Err(Diagnostic::error(
+                None,
+                &format!("unknown start of token: {}", c),
+                span(i, i + c.len_utf8()),
+                "",
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// The contents of the string literal at the start of `rest` and the length
+// of the literal including its quotes
+fn string(rest: &str) -> Option<(String, usize)> {
+    let mut text = String::new();
+    let mut chars = rest.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((text, i + 1)),
+            '\\' => match chars.next()?.1 {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                other => text.push(other),
+            },
+            _ => text.push(c),
+        }
+    }
+
+    None
+}
+
+fn span(start: usize, end: usize) -> Span {
+    Span { start, end }
+}
@@ -0,0 +1,114 @@
+// Project: references
+// Author: Greg Folker
+
+// A tiny Rust-like language with its own borrow checker, for trying out
+// ownership and borrowing without waiting on a full compile
+//
+// It understands `fn`, `let` and `let mut`, `String::from`, `&` and `&mut`,
+// blocks, function calls, `push_str`, `len` and `println!`, which is
+// enough to write every lesson in `references.rs` and its commented-out
+// errors. Errors are reported with the same codes and wording as `rustc`
+
+pub mod ast;
+pub mod check;
+pub mod diagnostic;
+pub mod interp;
+mod lexer;
+mod parser;
+pub mod step;
+
+use self::ast::Function;
+use self::check::Report;
+use self::diagnostic::Diagnostic;
+use self::interp::Machine;
+use self::step::Step;
+
+/// Toy versions of the lessons and of their commented-out errors, named
+/// after the lesson or `@compile_fail` example they are written from
+pub static PROGRAMS: &[(&str, &str)] = &[
+    ("borrowing", include_str!("programs/borrowing.toy")),
+    (
+        "mutable-references",
+        include_str!("programs/mutable-references.toy"),
+    ),
+    (
+        "mutable-aliasing",
+        include_str!("programs/mutable-aliasing.toy"),
+    ),
+    (
+        "scoped-borrows",
+        include_str!("programs/scoped-borrows.toy"),
+    ),
+    (
+        "shared-and-mutable",
+        include_str!("programs/shared-and-mutable.toy"),
+    ),
+    (
+        "dangling-references",
+        include_str!("programs/dangling-references.toy"),
+    ),
+    (
+        "double-mutable-borrow",
+        include_str!("programs/double-mutable-borrow.toy"),
+    ),
+    (
+        "shared-then-mutable",
+        include_str!("programs/shared-then-mutable.toy"),
+    ),
+    ("dangle", include_str!("programs/dangle.toy")),
+    (
+        "immutable-change",
+        include_str!("programs/immutable-change.toy"),
+    ),
+    (
+        "move-while-borrowed",
+        include_str!("programs/move-while-borrowed.toy"),
+    ),
+];
+
+pub struct Program {
+    /// The file name used in diagnostics
+    pub name: String,
+    pub source: String,
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn parse(name: &str, source: &str) -> Result<Program, Diagnostic> {
+        Ok(Program {
+            name: name.to_string(),
+            source: source.to_string(),
+            functions: parser::parse(source)?,
+        })
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    pub fn check(&self) -> Report {
+        check::check(self)
+    }
+
+    /// Runs a program that has passed `check` and returns what it printed
+    pub fn run(&self) -> Result<String, String> {
+        Machine::new(self).run()
+    }
+
+    /// Runs a program that has passed `check` one statement at a time
+    pub fn steps(&self) -> Result<Vec<Step>, String> {
+        step::steps(self)
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        diagnostic.render(&self.name, &self.source)
+    }
+}
+
+/// The source of one of the bundled programs
+pub fn find(name: &str) -> Option<&'static str> {
+    PROGRAMS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, source)| *source)
+}
@@ -0,0 +1,341 @@
+// Project: references
+// Author: Greg Folker
+
+use super::ast::{Block, Expr, ExprKind, Function, Param, Span, Stmt, Type};
+use super::diagnostic::Diagnostic;
+use super::lexer::{self, Token};
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+    // Where the source ends, for errors about running out of tokens
+    end: Span,
+}
+
+pub fn parse(source: &str) -> Result<Vec<Function>, Diagnostic> {
+    let mut parser = Parser {
+        tokens: lexer::tokenize(source)?,
+        pos: 0,
+        end: Span {
+            start: source.len(),
+            end: source.len(),
+        },
+    };
+
+    let mut functions = Vec::new();
+    while parser.pos < parser.tokens.len() {
+        functions.push(parser.function()?);
+    }
+
+    Ok(functions)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |&(_, span)| span)
+    }
+
+    fn previous_span(&self) -> Span {
+        self.tokens[self.pos.saturating_sub(1)].1
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(q)) if *q == p)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(name)) if name == keyword)
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        let found = self.is_punct(p);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let found = match self.peek() {
+            Some(Token::Ident(name)) => format!("`{}`", name),
+            Some(Token::Str(_)) => String::from("string literal"),
+            Some(Token::Int(n)) => format!("`{}`", n),
+            Some(Token::Punct(p)) => format!("`{}`", p),
+            None => String::from("end of file"),
+        };
+
+        Diagnostic::error(
+            None,
+            &format!("expected {}, found {}", expected, found),
+            self.span(),
+            &format!("expected {}", expected),
+        )
+    }
+
+    fn expect_punct(&mut self, p: &str) -> Result<Span, Diagnostic> {
+        let span = self.span();
+        if self.eat_punct(p) {
+            Ok(span)
+        } else {
+            Err(self.unexpected(&format!("`{}`", p)))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Span, Diagnostic> {
+        let span = self.span();
+        if self.eat_keyword(keyword) {
+            Ok(span)
+        } else {
+            Err(self.unexpected(&format!("`{}`", keyword)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<(String, Span), Diagnostic> {
+        match self.peek() {
+            Some(Token::Ident(name)) if !is_reserved(name) => {
+                let name = name.clone();
+                let span = self.span();
+                self.pos += 1;
+                Ok((name, span))
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn function(&mut self) -> Result<Function, Diagnostic> {
+        self.expect_keyword("fn")?;
+        let (name, name_span) = self.ident()?;
+
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        while !self.eat_punct(")") {
+            let (name, span) = self.ident()?;
+            self.expect_punct(":")?;
+            let (ty, ty_span) = self.ty()?;
+            params.push(Param {
+                name,
+                span,
+                ty,
+                ty_span,
+            });
+
+            if !self.is_punct(")") {
+                self.expect_punct(",")?;
+            }
+        }
+
+        let ret = if self.eat_punct("->") {
+            Some(self.ty()?)
+        } else {
+            None
+        };
+
+        Ok(Function {
+            name,
+            name_span,
+            params,
+            ret,
+            body: self.block()?,
+        })
+    }
+
+    fn ty(&mut self) -> Result<(Type, Span), Diagnostic> {
+        let start = self.span();
+
+        if self.eat_punct("&") {
+            let mutable = self.eat_keyword("mut");
+
+            // String literals are `&str`, which is its own type here
+            if !mutable && self.is_keyword("str") {
+                let end = self.span();
+                self.pos += 1;
+                return Ok((Type::Str, start.to(end)));
+            }
+
+            let (inner, end) = self.ty()?;
+            return Ok((Type::Ref(mutable, Box::new(inner)), start.to(end)));
+        }
+
+        let ty = match self.peek() {
+            Some(Token::Ident(name)) if name == "String" => Type::String,
+            Some(Token::Ident(name)) if name == "usize" => Type::Usize,
+            _ => return Err(self.unexpected("`String`, `usize` or a reference")),
+        };
+        self.pos += 1;
+
+        Ok((ty, start))
+    }
+
+    fn block(&mut self) -> Result<Block, Diagnostic> {
+        let start = self.expect_punct("{")?;
+        let mut stmts = Vec::new();
+        let mut tail = None;
+
+        loop {
+            if self.is_punct("}") {
+                break;
+            }
+
+            if self.is_punct("{") {
+                stmts.push(Stmt::Block(self.block()?));
+                continue;
+            }
+
+            if self.is_keyword("let") {
+                stmts.push(self.let_stmt()?);
+                continue;
+            }
+
+            let expr = self.expr()?;
+            if self.eat_punct(";") {
+                stmts.push(Stmt::Expr(expr));
+            } else if self.is_punct("}") {
+                tail = Some(expr);
+            } else {
+                return Err(self.unexpected("`;` or `}`"));
+            }
+        }
+
+        let end = self.expect_punct("}")?;
+        Ok(Block {
+            stmts,
+            tail,
+            span: start.to(end),
+        })
+    }
+
+    fn let_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.expect_keyword("let")?;
+        let mutable = self.eat_keyword("mut");
+        let (name, name_span) = self.ident()?;
+        self.expect_punct("=")?;
+        let init = self.expr()?;
+        let end = self.expect_punct(";")?;
+
+        Ok(Stmt::Let {
+            name,
+            mutable,
+            name_span,
+            init,
+            span: start.to(end),
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.span();
+
+        if self.eat_punct("&") {
+            let mutable = self.eat_keyword("mut");
+            let inner = self.expr()?;
+            let span = start.to(inner.span);
+            return Ok(Expr {
+                kind: ExprKind::Ref(mutable, Box::new(inner)),
+                span,
+            });
+        }
+
+        let mut expr = self.primary()?;
+        while self.eat_punct(".") {
+            let (method, _) = self.ident()?;
+            let args = self.args()?;
+            let span = expr.span.to(self.previous_span());
+            expr = Expr {
+                kind: ExprKind::Method(Box::new(expr), method, args),
+                span,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.span();
+
+        let kind = match self.peek().cloned() {
+            Some(Token::Str(text)) => {
+                self.pos += 1;
+                ExprKind::Str(text)
+            }
+            Some(Token::Int(n)) => {
+                self.pos += 1;
+                ExprKind::Int(n)
+            }
+            Some(Token::Ident(name)) if name == "String" => {
+                self.pos += 1;
+                self.expect_punct("::")?;
+                self.expect_keyword("from")?;
+                self.expect_punct("(")?;
+                let text = match self.peek().cloned() {
+                    Some(Token::Str(text)) => text,
+                    _ => return Err(self.unexpected("string literal")),
+                };
+                self.pos += 1;
+                self.expect_punct(")")?;
+                ExprKind::StringFrom(text)
+            }
+            Some(Token::Ident(name)) if name == "println" => {
+                self.pos += 1;
+                self.expect_punct("!")?;
+                self.expect_punct("(")?;
+                let format = match self.peek().cloned() {
+                    Some(Token::Str(text)) => text,
+                    _ => return Err(self.unexpected("format string")),
+                };
+                self.pos += 1;
+
+                let mut args = Vec::new();
+                while self.eat_punct(",") {
+                    args.push(self.expr()?);
+                }
+                self.expect_punct(")")?;
+                ExprKind::Println(format, args)
+            }
+            Some(Token::Ident(_)) => {
+                let (name, _) = self.ident()?;
+                if self.is_punct("(") {
+                    ExprKind::Call(name, self.args()?)
+                } else {
+                    ExprKind::Var(name)
+                }
+            }
+            _ => return Err(self.unexpected("expression")),
+        };
+
+        Ok(Expr {
+            kind,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn args(&mut self) -> Result<Vec<Expr>, Diagnostic> {
+        self.expect_punct("(")?;
+        let mut args = Vec::new();
+
+        while !self.eat_punct(")") {
+            args.push(self.expr()?);
+            if !self.is_punct(")") {
+                self.expect_punct(",")?;
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    matches!(name, "fn" | "let" | "mut")
+}
@@ -0,0 +1,504 @@
+// Project: references
+// Author: Greg Folker
+
+// Runs `main` of a toy program one statement at a time and records the
+// state after each one: which variables own a `String`, which have been
+// moved out of, which references are still live and what they borrow,
+// and what was dropped or printed along the way
+//
+// A reference is live until the last statement that uses it, the same
+// rule the checker uses, so a borrow is shown as over as soon as it is no
+// longer needed even if the variable holding it is still in scope
+
+use super::ast::{Block, Expr, ExprKind, Span, Stmt};
+use super::diagnostic::position;
+use super::interp::{Event, Frame, Machine, Value};
+use super::Program;
+
+/// The state of `main` after one statement, or after the end of a block
+pub struct Step {
+    pub line: usize,
+    /// The source of the line the step starts on
+    pub code: String,
+    pub variables: Vec<Variable>,
+    /// Borrows taken during the step that ended along with it, such as
+    /// `&mut s` passed to a function
+    pub released: Vec<String>,
+    /// Variables that went out of scope at the end of a block
+    pub out_of_scope: Vec<String>,
+    /// The owner and contents of each `String` freed during the step
+    pub dropped: Vec<(String, String)>,
+    pub printed: Vec<String>,
+}
+
+pub struct Variable {
+    pub name: String,
+    pub state: State,
+}
+
+pub enum State {
+    Owned {
+        text: String,
+        /// Live references to it, and whether each is `&mut`
+        borrowed_by: Vec<(String, bool)>,
+    },
+    Moved,
+    Ref {
+        owner: String,
+        mutable: bool,
+        /// The line of the reference's last use, `None` once it has passed
+        last_use: Option<usize>,
+    },
+    Value(String),
+}
+
+// A statement of `main` in the order it runs, with the variables it uses
+struct Point {
+    line: usize,
+    uses: Vec<String>,
+    binds: Option<String>,
+}
+
+struct Stepper<'p> {
+    program: &'p Program,
+    machine: Machine<'p>,
+    points: Vec<Point>,
+    // The index in `points` of the statement that ran last
+    at: usize,
+    steps: Vec<Step>,
+    // The variable each reference borrowed from when it was taken, which
+    // stays its owner even if the `String` is later moved to another
+    owners: Vec<(String, String)>,
+}
+
+/// Runs `main` of a program that has passed `check`, one step at a time
+pub fn steps(program: &Program) -> Result<Vec<Step>, String> {
+    let main = program
+        .function("main")
+        .ok_or_else(|| String::from("`main` function not found"))?;
+
+    let mut points = Vec::new();
+    flatten(program, &main.body, &mut points);
+
+    let mut stepper = Stepper {
+        program,
+        machine: Machine::new(program),
+        points,
+        at: 0,
+        steps: Vec::new(),
+        owners: Vec::new(),
+    };
+    stepper.machine.frames.push(Frame {
+        function: main.name.clone(),
+        slots: Vec::new(),
+    });
+    stepper.block(&main.body);
+
+    match stepper.machine.error.take() {
+        Some(error) => Err(error),
+        None => Ok(stepper.steps),
+    }
+}
+
+pub fn render(steps: &[Step]) -> String {
+    let mut out = String::new();
+
+    for step in steps {
+        out.push_str(&format!("line {}: {}\n", step.line, step.code));
+
+        let described: Vec<(String, String)> = step
+            .variables
+            .iter()
+            .map(|v| (v.name.clone(), describe(&v.state)))
+            .collect();
+        let width = described.iter().map(|(name, _)| name.len()).max();
+        for (name, text) in &described {
+            out.push_str(&format!(
+                "    {:width$}  {}\n",
+                name,
+                text,
+                width = width.unwrap_or(0)
+            ));
+        }
+
+        let loans = loans(&step.variables);
+        out.push_str(&format!(
+            "  loans: {}\n",
+            if loans.is_empty() {
+                String::from("none")
+            } else {
+                loans.join(", ")
+            }
+        ));
+
+        for borrow in &step.released {
+            out.push_str(&format!(
+                "  released: `{}` only lasted for this statement\n",
+                borrow
+            ));
+        }
+        if !step.out_of_scope.is_empty() {
+            out.push_str(&format!(
+                "  out of scope: {}\n",
+                step.out_of_scope.join(", ")
+            ));
+        }
+        for (name, text) in &step.dropped {
+            out.push_str(&format!(
+                "  dropped: `{}` ({:?}), freeing its heap memory\n",
+                name, text
+            ));
+        }
+        for line in &step.printed {
+            out.push_str(&format!("  printed: {}\n", line));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn describe(state: &State) -> String {
+    match state {
+        State::Owned { text, borrowed_by } => {
+            let names: Vec<&str> = borrowed_by.iter().map(|(n, _)| n.as_str()).collect();
+            match borrowed_by.first() {
+                None => format!("String {:?}, owned", text),
+                Some((_, true)) => {
+                    format!(
+                        "String {:?}, mutably borrowed by {}",
+                        text,
+                        names.join(", ")
+                    )
+                }
+                Some((_, false)) => format!("String {:?}, borrowed by {}", text, names.join(", ")),
+            }
+        }
+        State::Moved => String::from("moved out, owns nothing"),
+        State::Ref {
+            owner,
+            mutable,
+            last_use,
+        } => {
+            let ty = if *mutable { "&mut String" } else { "&String" };
+            match last_use {
+                Some(line) => format!("{} to `{}`, live until line {}", ty, owner, line),
+                None => format!(
+                    "{} to `{}`, no longer used, so the borrow is over",
+                    ty, owner
+                ),
+            }
+        }
+        State::Value(text) => text.clone(),
+    }
+}
+
+fn loans(variables: &[Variable]) -> Vec<String> {
+    variables
+        .iter()
+        .filter_map(|v| match &v.state {
+            State::Ref {
+                owner,
+                mutable,
+                last_use: Some(_),
+            } => Some(format!(
+                "`{}` holds {}{}",
+                v.name,
+                if *mutable { "&mut " } else { "&" },
+                owner
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Stepper<'_> {
+    fn line(&self, span: Span) -> usize {
+        position(&self.program.source, span.start).0
+    }
+
+    fn source_line(&self, line: usize) -> String {
+        self.program
+            .source
+            .lines()
+            .nth(line - 1)
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    }
+
+    fn block(&mut self, block: &Block) {
+        let depth = self.machine.frames[0].slots.len();
+
+        for stmt in &block.stmts {
+            if self.machine.error.is_some() {
+                return;
+            }
+            if let Stmt::Block(inner) = stmt {
+                self.block(inner);
+                continue;
+            }
+
+            let events = self.machine.events.len();
+            self.machine.stmt(stmt);
+            self.at += 1;
+            if let Stmt::Let { name, init, .. } = stmt {
+                self.record_owner(name, init);
+            }
+
+            let released = match stmt {
+                Stmt::Let { init, .. } if matches!(init.kind, ExprKind::Ref(..)) => Vec::new(),
+                Stmt::Let { init, .. } | Stmt::Expr(init) => self.temporary_borrows(init),
+                Stmt::Block(_) => Vec::new(),
+            };
+            let line = self.line(stmt.span());
+            self.record(line, events, released, Vec::new());
+        }
+
+        if self.machine.error.is_some() {
+            return;
+        }
+        if let Some(tail) = &block.tail {
+            let events = self.machine.events.len();
+            self.machine.tail(tail);
+            self.at += 1;
+
+            let line = self.line(tail.span);
+            let released = self.temporary_borrows(tail);
+            self.record(line, events, released, Vec::new());
+        }
+
+        let events = self.machine.events.len();
+        let names: Vec<String> = self.machine.frames[0].slots[depth..]
+            .iter()
+            .filter(|slot| !slot.name.starts_with('<'))
+            .map(|slot| slot.name.clone())
+            .rev()
+            .collect();
+        self.machine.drop_slots(depth);
+
+        let line = position(&self.program.source, block.span.end.saturating_sub(1)).0;
+        self.record(line, events, Vec::new(), names);
+    }
+
+    // `&s` and `&mut s` in an expression that are not kept in a variable
+    fn temporary_borrows(&self, expr: &Expr) -> Vec<String> {
+        let mut borrows = Vec::new();
+        walk(expr, &mut |expr| {
+            if let ExprKind::Ref(..) = expr.kind {
+                borrows.push(self.program.source[expr.span.start..expr.span.end].to_string());
+            }
+        });
+        borrows
+    }
+
+    fn record(
+        &mut self,
+        line: usize,
+        events: usize,
+        released: Vec<String>,
+        out_of_scope: Vec<String>,
+    ) {
+        let mut dropped = Vec::new();
+        let mut printed = Vec::new();
+        for event in &self.machine.events[events..] {
+            match event {
+                Event::Print(text) => printed.push(text.clone()),
+                Event::Drop { name, text } if name.starts_with('<') => {
+                    dropped.push((String::from("a temporary"), text.clone()))
+                }
+                Event::Drop { name, text } => dropped.push((name.clone(), text.clone())),
+            }
+        }
+
+        let step = Step {
+            line,
+            code: self.source_line(line),
+            variables: self.variables(),
+            released,
+            out_of_scope,
+            dropped,
+            printed,
+        };
+        self.steps.push(step);
+    }
+
+    // Remembers what the reference just bound to `name` borrows from. A
+    // copy of another reference borrows from the same owner as it does
+    fn record_owner(&mut self, name: &str, init: &Expr) {
+        self.owners.retain(|(reference, _)| reference != name);
+
+        let cell = match self.machine.frames[0].slots.last() {
+            Some(slot) if slot.name == name => match slot.value {
+                Some(Value::Ref(cell, _)) => cell,
+                _ => return,
+            },
+            _ => return,
+        };
+        let copied = match &init.kind {
+            ExprKind::Var(other) => self.owner(other),
+            ExprKind::Ref(_, inner) => match &inner.kind {
+                ExprKind::Var(other) => self.owner(other),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let owner = copied.unwrap_or_else(|| self.current_owner(cell));
+        self.owners.push((name.to_string(), owner));
+    }
+
+    fn owner(&self, reference: &str) -> Option<String> {
+        self.owners
+            .iter()
+            .find(|(name, _)| name == reference)
+            .map(|(_, owner)| owner.clone())
+    }
+
+    // The variable that holds the `String` in `cell` right now
+    fn current_owner(&self, cell: usize) -> String {
+        self.machine.frames[0]
+            .slots
+            .iter()
+            .rev()
+            .find(|other| other.value == Some(Value::Str(cell)))
+            .map_or(String::from("a temporary"), |owner| {
+                if owner.name.starts_with('<') {
+                    String::from("a temporary")
+                } else {
+                    owner.name.clone()
+                }
+            })
+    }
+
+    fn variables(&self) -> Vec<Variable> {
+        let slots = &self.machine.frames[0].slots;
+        let mut variables: Vec<Variable> = Vec::new();
+
+        for slot in slots.iter().filter(|slot| !slot.name.starts_with('<')) {
+            let state = match &slot.value {
+                None => State::Moved,
+                Some(Value::Str(cell)) => State::Owned {
+                    text: self.machine.heap[*cell].text.clone(),
+                    borrowed_by: Vec::new(),
+                },
+                Some(Value::Ref(cell, mutable)) => State::Ref {
+                    owner: self
+                        .owner(&slot.name)
+                        .unwrap_or_else(|| self.current_owner(*cell)),
+                    mutable: *mutable,
+                    last_use: self.last_use(&slot.name),
+                },
+                Some(Value::Usize(n)) => State::Value(format!("usize {}", n)),
+                Some(Value::Text(text)) => State::Value(format!("&str {:?}", text)),
+                Some(Value::Unit) => State::Value(String::from("()")),
+            };
+            variables.push(Variable {
+                name: slot.name.clone(),
+                state,
+            });
+        }
+
+        // Fill in who is borrowing each owner through a live reference
+        let live: Vec<(String, String, bool)> = variables
+            .iter()
+            .filter_map(|v| match &v.state {
+                State::Ref {
+                    owner,
+                    mutable,
+                    last_use: Some(_),
+                } => Some((owner.clone(), v.name.clone(), *mutable)),
+                _ => None,
+            })
+            .collect();
+        for variable in &mut variables {
+            if let State::Owned { borrowed_by, .. } = &mut variable.state {
+                for (owner, name, mutable) in &live {
+                    if *owner == variable.name {
+                        borrowed_by.push((name.clone(), *mutable));
+                    }
+                }
+            }
+        }
+
+        variables
+    }
+
+    // The line of the last statement still to run that uses `name`,
+    // stopping if it is bound to something else first
+    fn last_use(&self, name: &str) -> Option<usize> {
+        let mut last = None;
+
+        for point in &self.points[self.at.min(self.points.len())..] {
+            if point.uses.iter().any(|used| used == name) {
+                last = Some(point.line);
+            }
+            if point.binds.as_deref() == Some(name) {
+                break;
+            }
+        }
+
+        last
+    }
+}
+
+// The statements of a block in the order they run, nested blocks included
+fn flatten(program: &Program, block: &Block, points: &mut Vec<Point>) {
+    let line = |span: Span| position(&program.source, span.start).0;
+
+    for stmt in &block.stmts {
+        let (init, binds) = match stmt {
+            Stmt::Let { name, init, .. } => (init, Some(name.clone())),
+            Stmt::Expr(expr) => (expr, None),
+            Stmt::Block(inner) => {
+                flatten(program, inner, points);
+                continue;
+            }
+        };
+
+        points.push(Point {
+            line: line(stmt.span()),
+            uses: uses(init),
+            binds,
+        });
+    }
+
+    if let Some(tail) = &block.tail {
+        points.push(Point {
+            line: line(tail.span),
+            uses: uses(tail),
+            binds: None,
+        });
+    }
+}
+
+fn uses(expr: &Expr) -> Vec<String> {
+    let mut names = Vec::new();
+    walk(expr, &mut |expr| {
+        if let ExprKind::Var(name) = &expr.kind {
+            names.push(name.clone());
+        }
+    });
+    names
+}
+
+// Calls `visit` on `expr` and everything inside it
+fn walk<'e>(expr: &'e Expr, visit: &mut dyn FnMut(&'e Expr)) {
+    visit(expr);
+
+    match &expr.kind {
+        ExprKind::Ref(_, inner) => walk(inner, visit),
+        ExprKind::Call(_, args) | ExprKind::Println(_, args) => {
+            for arg in args {
+                walk(arg, visit);
+            }
+        }
+        ExprKind::Method(receiver, _, args) => {
+            walk(receiver, visit);
+            for arg in args {
+                walk(arg, visit);
+            }
+        }
+        ExprKind::StringFrom(_) | ExprKind::Str(_) | ExprKind::Int(_) | ExprKind::Var(_) => {}
+    }
+}
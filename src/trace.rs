@@ -0,0 +1,125 @@
+// Project: references
+// Author: Greg Folker
+
+// An instrumented `String` that reports when it is created, borrowed,
+// moved and dropped, so the lessons can show when those things happen
+// instead of only describing them in comments
+
+use std::cell::Cell;
+use std::fmt;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Prints one line of the trace, indented to the current scope depth
+pub fn log(message: &str) {
+    let depth = DEPTH.with(|depth| depth.get());
+    println!("[trace] {}{}", "  ".repeat(depth), message);
+}
+
+/// Marks the start of a scope, which ends when the returned guard is
+/// dropped. Declare it before anything else in the scope so that it is
+/// dropped after everything else in it
+pub fn scope(name: &str) -> Scope {
+    log(&format!("{{ {}", name));
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+
+    Scope {
+        name: name.to_string(),
+    }
+}
+
+pub struct Scope {
+    name: String,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+        log(&format!("}} {}", self.name));
+    }
+}
+
+/// A `String` that logs what happens to it. It dereferences to the
+/// `String` it wraps, so it can be passed to functions taking `&String`
+pub struct Traced {
+    name: String,
+    value: String,
+    // Set once the value has moved on to a new owner, which is then the
+    // one responsible for dropping it
+    moved: bool,
+}
+
+impl Traced {
+    pub fn new(name: &str, value: &str) -> Traced {
+        log(&format!("create `{}` = {:?}", name, value));
+
+        Traced {
+            name: name.to_string(),
+            value: value.to_string(),
+            moved: false,
+        }
+    }
+
+    /// Borrows the value for `by`, such as a reference or a parameter
+    pub fn borrow(&self, by: &str) -> &String {
+        log(&format!("borrow `{}` as &String for `{}`", self.name, by));
+        &self.value
+    }
+
+    /// Mutably borrows the value for `by`
+    pub fn borrow_mut(&mut self, by: &str) -> &mut String {
+        log(&format!(
+            "borrow `{}` as &mut String for `{}`",
+            self.name, by
+        ));
+        &mut self.value
+    }
+
+    /// Moves the value to a new owner called `to`. The old name no longer
+    /// owns anything, so nothing is dropped when it goes out of scope
+    pub fn moved_to(mut self, to: &str) -> Traced {
+        log(&format!("move `{}` to `{}`", self.name, to));
+        self.moved = true;
+
+        Traced {
+            name: to.to_string(),
+            value: mem::take(&mut self.value),
+            moved: false,
+        }
+    }
+}
+
+impl Deref for Traced {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.value
+    }
+}
+
+impl DerefMut for Traced {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.value
+    }
+}
+
+impl fmt::Display for Traced {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl Drop for Traced {
+    fn drop(&mut self) {
+        if !self.moved {
+            log(&format!(
+                "drop `{}` ({:?}), freeing its heap memory",
+                self.name, self.value
+            ));
+        }
+    }
+}
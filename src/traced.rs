@@ -0,0 +1,99 @@
+// Project: references
+// Author: Greg Folker
+
+// The lessons from `references.rs` again, using `Traced` in place of
+// `String` so every creation, borrow, move and drop is printed as it
+// happens. Each one follows the lesson of the same name
+
+// Borrows are of the `String` itself, as in `references.rs`
+#![allow(clippy::ptr_arg)]
+
+use crate::trace::{self, Traced};
+
+pub fn borrowing() {
+    let _scope = trace::scope("borrowing");
+    let s1 = Traced::new("s1", "Hello");
+
+    let len = calculate_length(s1.borrow("s"));
+
+    println!("The length of '{}' is {}", s1, len);
+}
+
+pub fn mutable_references() {
+    let _scope = trace::scope("mutable_references");
+    let mut s = Traced::new("s", "Hello");
+
+    change(s.borrow_mut("some_string"));
+
+    println!("{}", s);
+}
+
+pub fn mutable_aliasing() {
+    let _scope = trace::scope("mutable_aliasing");
+    let mut s = Traced::new("s", "Hello");
+
+    let r1 = s.borrow_mut("r1");
+
+    println!("{}", r1);
+}
+
+pub fn scoped_borrows() {
+    let _scope = trace::scope("scoped_borrows");
+    let mut s = Traced::new("s", "Hello");
+
+    {
+        let _scope = trace::scope("block");
+        let r1 = s.borrow_mut("r1");
+        println!("{}", r1);
+    }
+
+    let r2 = s.borrow_mut("r2");
+    println!("{}", r2);
+}
+
+pub fn shared_and_mutable() {
+    let _scope = trace::scope("shared_and_mutable");
+    let mut s = Traced::new("s", "Hello");
+
+    let r1 = s.borrow("r1");
+    let r2 = s.borrow("r2");
+
+    println!("r1={} and r2={}", r1, r2);
+
+    let r3 = s.borrow_mut("r3");
+    println!("r3={}", r3);
+}
+
+pub fn dangling_references() {
+    let _scope = trace::scope("dangling_references");
+
+    let reference_to_something = no_dangle("reference_to_something");
+
+    println!("reference_to_something={}", reference_to_something);
+}
+
+fn calculate_length(s: &String) -> usize {
+    let _scope = trace::scope("calculate_length");
+    let len = s.len();
+
+    trace::log("`s` is only borrowed, so leaving this scope does not drop it");
+    len
+}
+
+fn change(some_string: &mut String) {
+    let _scope = trace::scope("change");
+
+    some_string.push_str(", world");
+    trace::log(&format!(
+        "`some_string` changed the borrowed value to {:?}",
+        some_string
+    ));
+}
+
+// `owner` is only there to give the caller's variable a name in the trace
+fn no_dangle(owner: &str) -> Traced {
+    let _scope = trace::scope("no_dangle");
+    let s = Traced::new("s", "Hello");
+
+    s.moved_to(owner)
+}
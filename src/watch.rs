@@ -0,0 +1,57 @@
+// Project: references
+// Author: Greg Folker
+
+// Noticing when a student saves a file by reading it again every so
+// often. Polling needs nothing from the operating system beyond reading
+// the file, and half a second is quick enough to feel immediate
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait between reads of the file
+pub const INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct Watcher {
+    path: PathBuf,
+    // What the file held when it was last read
+    last: Option<String>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Watcher {
+        Watcher {
+            path: path.to_path_buf(),
+            last: None,
+        }
+    }
+
+    /// The file's contents if they have changed since the last poll, or
+    /// if this is the first. A file that is missing for a moment, as it
+    /// is while some editors save, counts as unchanged
+    pub fn poll(&mut self) -> io::Result<Option<String>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if self.last.as_ref() == Some(&text) {
+            return Ok(None);
+        }
+        self.last = Some(text.clone());
+        Ok(Some(text))
+    }
+
+    /// Waits for the file to change and returns what it holds now
+    pub fn wait(&mut self) -> io::Result<String> {
+        loop {
+            if let Some(text) = self.poll()? {
+                return Ok(text);
+            }
+            thread::sleep(INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Project: references
+// Author: Greg Folker
+
+// Commentary refers to lines through anchors, so a missing anchor or a
+// line number typed in by hand is a mistake in the lessons. The lines they
+// resolve to are the ones `show` numbers its listing with
+
+use references::anchors;
+use references::lesson;
+
+#[test]
+fn every_anchor_resolves() {
+    let errors: Vec<String> = anchors::check().iter().map(|e| e.to_string()).collect();
+
+    assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+}
+
+#[test]
+fn shown_source_is_numbered_and_has_no_markers() {
+    let lesson = lesson::find("shared-and-mutable").unwrap();
+    let source = lesson.source().unwrap();
+
+    assert!(!source.contains("@compile_fail"), "{}", source);
+    let line = source
+        .lines()
+        .find(|line| line.contains("println!(\"r1={} and r2={}\""))
+        .unwrap();
+    assert!(line.trim_start().starts_with("82 | "), "{}", line);
+    assert!(source.contains("(Line 82)"), "{}", source);
+}
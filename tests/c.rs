@@ -0,0 +1,80 @@
+// Project: references
+// Author: Greg Folker
+
+// The C programs print what the Rust lessons they mirror print, and the
+// dangling pointer in `dangle.c` is caught by AddressSanitizer. Showing
+// the Rust side never gives away an error with its marker. Machines
+// without a C compiler, or without the sanitizer, skip what they cannot run
+
+use std::process::Command;
+
+use references::c;
+use references::examples;
+use references::lesson;
+use references::snapshot;
+
+fn have_compiler() -> bool {
+    let found = Command::new(c::compiler())
+        .arg("--version")
+        .output()
+        .is_ok();
+    if !found {
+        eprintln!("no C compiler found, skipping");
+    }
+    found
+}
+
+#[test]
+fn c_programs_print_what_the_rust_lessons_print() {
+    if !have_compiler() {
+        return;
+    }
+
+    for program in c::all().iter().filter(|p| p.example.is_none()) {
+        let outcome = program.run(false).unwrap();
+        let stderr = outcome.compile.stderr;
+        let run = outcome
+            .run
+            .unwrap_or_else(|| panic!("{} did not compile:\n{}", program.file, stderr));
+        assert!(run.success(), "{} {}", program.file, run.exit);
+
+        let lesson = lesson::find(program.lesson).unwrap();
+        let expected = snapshot::expected(lesson).unwrap();
+        assert_eq!(run.stdout, expected, "{}", program.file);
+    }
+}
+
+#[test]
+fn the_sanitizer_catches_the_dangling_pointer() {
+    if !have_compiler() || !c::sanitizer_available().unwrap() {
+        eprintln!("no AddressSanitizer, skipping");
+        return;
+    }
+
+    let dangle = c::find("dangle").unwrap();
+    let run = dangle.run(true).unwrap().run.unwrap();
+    assert!(!run.success());
+
+    let report = c::report(&run.stderr);
+    assert!(report.contains("heap-use-after-free"), "{}", run.stderr);
+    assert!(report.lines().last().unwrap().starts_with("SUMMARY: "));
+    assert!(report.contains("dangle.c:24"), "{}", report);
+}
+
+#[test]
+fn comparisons_leave_out_the_markers() {
+    if !have_compiler() {
+        return;
+    }
+
+    for program in c::all() {
+        let output = Command::new(env!("CARGO_BIN_EXE_references"))
+            .args(["c", program.name])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", program.name);
+        assert!(stdout.contains("In Rust"), "{}", stdout);
+        assert!(!stdout.contains(examples::MARKER), "{}", stdout);
+    }
+}
@@ -0,0 +1,96 @@
+// Project: references
+// Author: Greg Folker
+
+// A snippet is compiled the first time it is seen and comes from the
+// cache after that, with the same verdict. Changing the snippet changes
+// its entry, snippets compiled together come back in order, and the least
+// recently used entries are the ones evicted. A `rustc` that was killed
+// has given no verdict, so nothing is remembered for it, and a
+// half-written entry is never evicted
+
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use references::cache::Cache;
+use references::sandbox::Exit;
+
+const FINE: &str = "fn main() {}\n";
+const BROKEN: &str =
+    "fn main() {\n    let s = String::new();\n    let r = &mut s;\n    r.clear();\n}\n";
+
+#[test]
+fn verdicts_are_remembered_in_order_and_evicted_when_least_recently_used() {
+    let dir = env::temp_dir().join(format!("references-cache-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let cache = Cache::at(&dir, 2);
+
+    let first = cache.compile("broken", BROKEN).unwrap();
+    assert!(!first.cached && !first.success);
+    assert_eq!(first.exit, Exit::Code(1));
+    assert_eq!(first.codes, ["E0596"]);
+
+    let again = cache.compile("broken", BROKEN).unwrap();
+    assert!(again.cached);
+    assert_eq!(
+        (again.success, &again.codes, &again.stderr),
+        (first.success, &first.codes, &first.stderr)
+    );
+
+    let changed = cache
+        .compile("broken", &BROKEN.replace("let s", "let mut s"))
+        .unwrap();
+    assert!(!changed.cached && changed.success);
+
+    // Another process's entry that is still being written is left alone,
+    // and does not count towards the capacity
+    let writing = dir.join(format!("{:032x}.tmp1", 0));
+    fs::write(&writing, "").unwrap();
+
+    // `broken` is used again after the changed snippet was stored, so the
+    // changed snippet is the one evicted to make room for `fine`
+    let outcomes = cache
+        .compile_all(&[("fine", FINE), ("broken", BROKEN)])
+        .unwrap();
+    assert_eq!(
+        outcomes.iter().map(|o| o.success).collect::<Vec<_>>(),
+        [true, false]
+    );
+    assert!(!outcomes[0].cached && outcomes[1].cached);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+    assert!(writing.exists());
+    assert!(cache.compile("fine", FINE).unwrap().cached);
+    assert!(
+        !cache
+            .compile("broken", &BROKEN.replace("let s", "let mut s"))
+            .unwrap()
+            .cached
+    );
+
+    #[cfg(unix)]
+    killed_compilers_are_not_remembered(&dir);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Part of the test above rather than a test of its own, since it changes
+// `RUSTC` for the whole process
+#[cfg(unix)]
+fn killed_compilers_are_not_remembered(dir: &Path) {
+    let cache = Cache::at(&dir.join("killed"), 10);
+
+    // A `rustc` that kills itself the way the sandbox would
+    let rustc = dir.join("rustc");
+    fs::write(&rustc, "#!/bin/sh\nkill -KILL $$\n").unwrap();
+    fs::set_permissions(&rustc, fs::Permissions::from_mode(0o755)).unwrap();
+    env::set_var("RUSTC", &rustc);
+    let killed = cache.compile("killed", BROKEN);
+    let again = cache.compile("killed", BROKEN);
+    env::remove_var("RUSTC");
+
+    let (killed, again) = (killed.unwrap(), again.unwrap());
+    assert!(matches!(killed.exit, Exit::Signal(_)), "{}", killed.exit);
+    assert!(!again.cached);
+}
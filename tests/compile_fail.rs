@@ -0,0 +1,43 @@
+// Project: references
+// Author: Greg Folker
+
+// Every commented-out "this is a compiler error" example in the lesson
+// files has to keep failing, and with the error code it is marked with.
+// They are compiled all at once through the verdict cache, so a run with
+// no changes to the lessons compiles none of them
+
+use references::cache::Cache;
+use references::examples;
+
+#[test]
+fn examples_fail_with_expected_code() {
+    let examples = examples::all().unwrap_or_else(|e| panic!("{}", e));
+    assert!(
+        !examples.is_empty(),
+        "no `@compile_fail` examples were found"
+    );
+
+    let snippets: Vec<(&str, &str)> = examples
+        .iter()
+        .map(|example| (example.id.as_str(), example.source.as_str()))
+        .collect();
+    let outcomes = Cache::open()
+        .compile_all(&snippets)
+        .unwrap_or_else(|e| panic!("could not run rustc: {}", e));
+
+    for (example, outcome) in examples.iter().zip(outcomes) {
+        assert!(
+            !outcome.success,
+            "'{}' compiled, but is expected to fail with {}:\n{}",
+            example.id, example.code, example.source
+        );
+        assert!(
+            outcome.codes.contains(&example.code),
+            "'{}' was expected to fail with {} but failed with {:?}:\n{}",
+            example.id,
+            example.code,
+            outcome.codes,
+            outcome.stderr
+        );
+    }
+}
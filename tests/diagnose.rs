@@ -0,0 +1,104 @@
+// Project: references
+// Author: Greg Folker
+
+// Every error code the catalog explains has to point at a lesson that
+// exists, and at examples that really fail with that code
+
+use std::env;
+use std::fs;
+
+use references::catalog;
+use references::diagnose;
+use references::examples;
+use references::json::{self, Value};
+use references::lesson;
+use references::toy::{self, Program};
+
+#[test]
+fn catalog_points_at_real_lessons_and_examples() {
+    for entry in catalog::all() {
+        assert!(
+            lesson::find(entry.lesson).is_some(),
+            "{}: no lesson '{}'",
+            entry.code,
+            entry.lesson
+        );
+
+        if let Some(id) = entry.example {
+            let example = examples::find(id).unwrap().expect(id);
+            assert_eq!(example.code, entry.code, "example '{}'", id);
+        }
+
+        if let Some(name) = entry.toy {
+            let source = toy::find(name).expect(name);
+            let program = Program::parse(name, source)
+                .unwrap_or_else(|e| panic!("\n{}", e.render(name, source)));
+            let codes: Vec<&str> = program
+                .check()
+                .diagnostics
+                .iter()
+                .filter_map(|d| d.code)
+                .collect();
+            assert_eq!(codes, [entry.code], "toy program '{}'", name);
+        }
+    }
+}
+
+#[test]
+fn examples_are_explained_by_their_own_lesson() {
+    let dir = env::temp_dir().join(format!("references-diagnose-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    for example in examples::all().unwrap() {
+        let path = dir.join(format!("{}.rs", example.id));
+        fs::write(&path, &example.source).unwrap();
+
+        let diagnostics = diagnose::check(&path).unwrap();
+        let explained = diagnostics
+            .iter()
+            .find(|d| d.code.as_deref() == Some(example.code.as_str()))
+            .map(diagnose::explain)
+            .unwrap_or_else(|| panic!("'{}' did not fail with {}", example.id, example.code));
+
+        assert!(
+            explained.contains(&format!("references whatif {}", example.id)),
+            "{}",
+            explained
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn json_strings_are_unescaped() {
+    let value = json::parse(r#"{"text": "a \"b\"\né🦀", "n": [1, -2.5e1, null]}"#).unwrap();
+
+    assert_eq!(value.get("text").as_str(), Some("a \"b\"\né🦀"));
+    assert_eq!(
+        value.get("n").as_array(),
+        [Value::Number(1.0), Value::Number(-25.0), Value::Null]
+    );
+    assert!(json::parse("[1, 2").is_err());
+}
+
+#[test]
+fn catalog_is_searchable_by_code_and_keyword() {
+    let codes = |query: &str| -> Vec<&str> {
+        catalog::search(query)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.code)
+            .collect()
+    };
+
+    assert_eq!(codes("E0499"), ["E0499"]);
+    assert_eq!(codes("0596"), ["E0596"]);
+    assert!(codes("moved").contains(&"E0505"));
+    assert!(codes("no such thing anywhere").is_empty());
+
+    for entry in catalog::all() {
+        let explanation = entry.explanation().unwrap();
+        assert!(!explanation.trim().is_empty(), "{}", entry.code);
+    }
+}
@@ -0,0 +1,41 @@
+// Project: references
+// Author: Greg Folker
+
+// Every lesson and each of its errors is compiled as every edition. With
+// non-lexical lifetimes in all of them on a current compiler, the lessons
+// compile and their errors fail with the same codes whichever edition is
+// used
+
+use references::cache::Cache;
+use references::editions::{self, EDITIONS};
+use references::lesson;
+
+#[test]
+fn every_edition_gives_the_same_verdicts() {
+    let cache = Cache::open();
+
+    for lesson in lesson::all() {
+        let snippets = editions::lesson_snippets(lesson).unwrap();
+        let comparisons = editions::compare(&snippets, &cache).unwrap();
+        assert_eq!(comparisons.len(), snippets.len());
+        assert_eq!(comparisons[0].name, lesson.id);
+
+        for comparison in &comparisons {
+            assert_eq!(comparison.outcomes.len(), EDITIONS.len());
+            assert!(
+                comparison.verdicts_agree(),
+                "{}: {:?}",
+                comparison.name,
+                comparison
+                    .outcomes
+                    .iter()
+                    .map(editions::verdict)
+                    .collect::<Vec<_>>()
+            );
+        }
+        assert!(comparisons[0].outcomes.iter().all(|o| o.success));
+        assert!(comparisons[1..]
+            .iter()
+            .all(|c| c.outcomes.iter().all(|o| !o.success)));
+    }
+}
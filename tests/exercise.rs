@@ -0,0 +1,89 @@
+// Project: references
+// Author: Greg Folker
+
+// Every exercise has to start out broken in the way its example is, and a
+// real fix has to pass while a shortcut does not. Comments that name a
+// line name one in the exercise file, not in the lesson file
+
+use references::exercise::{self, Exercise};
+
+fn exercise(id: &str) -> Exercise {
+    exercise::find(id).unwrap().expect(id)
+}
+
+fn passes(exercise: &Exercise, code: &str) -> bool {
+    exercise
+        .grade(code)
+        .unwrap()
+        .iter()
+        .all(|check| check.passed)
+}
+
+#[test]
+fn starters_do_not_compile() {
+    let exercises = exercise::all().unwrap();
+    assert!(!exercises.is_empty());
+
+    for exercise in exercises {
+        let checks = exercise.grade(&exercise.starter).unwrap();
+        let compiles = checks
+            .iter()
+            .find(|check| check.description == "compiles")
+            .unwrap_or_else(|| panic!("'{}' was not compiled", exercise.example.id));
+
+        assert!(!compiles.passed, "'{}' compiled", exercise.example.id);
+        assert!(
+            compiles.details.contains(&exercise.example.code),
+            "{}",
+            compiles.details
+        );
+        assert!(
+            compiles
+                .diagnostics
+                .iter()
+                .any(|d| d.code.as_deref() == Some(exercise.example.code.as_str())),
+            "{}",
+            compiles.details
+        );
+    }
+}
+
+#[test]
+fn fixes_pass_and_shortcuts_do_not() {
+    let change = exercise("immutable-change");
+    let fixed = change
+        .starter
+        .replace("some_string: &String", "some_string: &mut String");
+    assert!(passes(&change, &fixed));
+
+    let aliasing = exercise("double-mutable-borrow");
+    let fixed = aliasing.starter.replace("let r2 = &mut s;", "");
+    assert!(passes(&aliasing, &fixed));
+
+    let cloned = aliasing
+        .starter
+        .replace("let r2 = &mut s;", "let r2 = &mut s.clone();");
+    assert!(!passes(&aliasing, &cloned));
+}
+
+#[test]
+fn starters_name_their_own_lines() {
+    let starter = exercise("shared-then-mutable").starter;
+    let lines: Vec<&str> = starter.lines().collect();
+    let named = |phrase: &str| -> &str {
+        let line = lines.iter().find(|line| line.contains(phrase)).unwrap();
+        let rest = &line[line.find("Line ").unwrap() + 5..];
+        let number: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        lines[number.parse::<usize>().unwrap() - 1].trim()
+    };
+
+    assert_eq!(named("is a compiler error"), "let r3 = &mut s;");
+    assert_eq!(
+        named("have last been used"),
+        r#"println!("r1={} and r2={}", r1, r2);"#
+    );
+
+    for exercise in exercise::all().unwrap() {
+        assert!(!exercise.starter.contains('@'), "{}", exercise.starter);
+    }
+}
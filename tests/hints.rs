@@ -0,0 +1,56 @@
+// Project: references
+// Author: Greg Folker
+
+// Every exercise has its three hints, ending with the working code it was
+// broken from, and each level unlocks only after enough failed checks
+
+use std::env;
+use std::fs;
+
+use references::exercise;
+use references::hints::{self, Level, Log};
+
+#[test]
+fn every_exercise_has_hints_ending_in_working_code() {
+    for exercise in exercise::all().unwrap() {
+        let hints = hints::for_exercise(&exercise.example.id).unwrap();
+        let levels: Vec<Level> = hints.iter().map(|hint| hint.level).collect();
+        assert_eq!(levels, hints::LEVELS, "{}", exercise.example.id);
+    }
+
+    let dangle = hints::for_exercise("dangle").unwrap();
+    assert!(dangle[2].text.contains("fn no_dangle() -> String {"));
+
+    let change = hints::for_exercise("immutable-change").unwrap();
+    assert!(change[2]
+        .text
+        .contains("fn change(some_string: &mut String) {"));
+    assert!(!change[2].text.contains("@compile_fail"));
+}
+
+#[test]
+fn hints_unlock_on_repeated_failures_and_are_recorded_per_student() {
+    assert_eq!(hints::unlocked(0), 0);
+    assert_eq!(hints::unlocked(1), 0);
+    assert_eq!(hints::unlocked(Level::Nudge.failures_needed()), 1);
+    assert_eq!(hints::unlocked(Level::NearSolution.failures_needed()), 3);
+    assert_eq!(hints::unlocked(100), 3);
+
+    let dir = env::temp_dir().join(format!("references-hints-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut log = Log::load(&dir).unwrap();
+    assert!(log.records.is_empty());
+    log.record("ada", "dangle").failures = 2;
+    log.record("ada", "dangle").hints_used = 1;
+    log.record("grace", "dangle").failures = 1;
+    log.save().unwrap();
+
+    let mut log = Log::load(&dir).unwrap();
+    assert_eq!(log.records.len(), 2);
+    assert_eq!(log.record("ada", "dangle").failures, 2);
+    assert_eq!(log.record("ada", "dangle").hints_used, 1);
+    assert_eq!(log.record("grace", "dangle").hints_used, 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+// Project: references
+// Author: Greg Folker
+
+// `run --inspect` has to show real addresses: a reference holds the
+// address of the value it refers to, wherever it is passed, and the sizes
+// it prints are the ones this machine uses. The lessons print what they
+// always print along with it
+
+use std::mem;
+use std::process::Command;
+
+use references::lesson;
+use references::progress;
+use references::snapshot;
+
+// Everything `run --inspect` prints for `lesson`
+fn inspect(lesson: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_references"))
+        .args(["run", "--inspect", lesson])
+        .env(progress::DISABLE_VARIABLE, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", lesson);
+
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// Every address on the `[inspect]` line that starts with `start`
+fn addresses<'a>(output: &'a str, start: &str) -> Vec<&'a str> {
+    let line = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("[inspect] "))
+        .find(|line| line.starts_with(start))
+        .unwrap_or_else(|| panic!("no line starting {:?} in\n{}", start, output));
+
+    line.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| word.starts_with("0x"))
+        .collect()
+}
+
+#[test]
+fn references_hold_the_address_of_what_they_borrow() {
+    let output = inspect("borrowing");
+
+    let s1 = addresses(&output, "`s1` lives at");
+    assert_eq!(s1.len(), 1);
+    assert_eq!(addresses(&output, "`&s1` is the address"), s1);
+
+    let outer = addresses(&output, "`&&s1` is the address");
+    assert_eq!(outer.len(), 2);
+    assert_ne!(outer[0], s1[0]);
+    assert_eq!(outer[1], s1[0]);
+
+    let parameter = addresses(&output, "in `calculate_length`");
+    assert_eq!(parameter[1], s1[0]);
+
+    let word = mem::size_of::<usize>();
+    for (start, size) in [
+        ("size_of::<String>()", 3 * word),
+        ("size_of::<&String>()", word),
+        ("size_of::<&str>()", 2 * word),
+    ] {
+        assert!(
+            output.contains(&format!("{} = {}:", start, size)),
+            "{}",
+            output
+        );
+    }
+}
+
+#[test]
+fn inspected_lessons_still_print_their_snapshots() {
+    for lesson in lesson::all().iter().filter(|l| l.inspected.is_some()) {
+        let printed: String = inspect(lesson.id)
+            .lines()
+            .filter(|line| !line.starts_with("[inspect] "))
+            .map(|line| format!("{}\n", line))
+            .collect();
+
+        assert_eq!(Some(printed.as_str()), snapshot::expected(lesson));
+    }
+}
@@ -0,0 +1,56 @@
+// Project: references
+// Author: Greg Folker
+
+// The memory diagrams are taken from the lesson values themselves, so the
+// pointers in them have to line up with the variables and buffers shown
+
+use references::lesson;
+use references::memory::{self, Diagram, Format, SlotValue};
+
+fn diagrams(id: &str) -> Vec<Diagram> {
+    let lesson = lesson::find(id).unwrap();
+    (lesson.memory.expect("every lesson has memory diagrams"))()
+}
+
+#[test]
+fn every_pointer_has_something_to_point_at() {
+    for lesson in lesson::all() {
+        for diagram in diagrams(lesson.id) {
+            for slot in diagram.frames.iter().flat_map(|frame| &frame.slots) {
+                match &slot.value {
+                    SlotValue::String { ptr, len, .. } => {
+                        let buffer = &diagram.heap[diagram.buffer_at(*ptr).unwrap()];
+                        assert_eq!(buffer.len, *len, "{}: {}", lesson.id, diagram.caption);
+                    }
+                    SlotValue::Ref { target, .. } => assert!(
+                        matches!(
+                            diagram.slot_at(*target).map(|s| &s.value),
+                            Some(SlotValue::String { .. })
+                        ),
+                        "{}: `{}` does not point at a `String`",
+                        lesson.id,
+                        slot.name
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn moving_a_string_out_keeps_its_heap_buffer() {
+    let diagrams = diagrams("dangling-references");
+    let heap: Vec<usize> = diagrams.iter().map(|d| d.heap[0].address).collect();
+
+    assert_eq!(heap.len(), 2);
+    assert_eq!(heap[0], heap[1]);
+}
+
+#[test]
+fn every_format_renders() {
+    let diagrams = diagrams("borrowing");
+
+    assert!(memory::render(&diagrams, Format::Ascii).contains("--> s1"));
+    assert!(memory::render(&diagrams, Format::Dot).starts_with("digraph memory {"));
+    assert!(memory::render(&diagrams, Format::Svg).starts_with("<svg "));
+}
@@ -0,0 +1,101 @@
+// Project: references
+// Author: Greg Folker
+
+// Progress has to survive being written out and read back, quiz scores
+// included, a file from an older version must still be read, and a file
+// from a newer version must be refused rather than misread
+
+use std::env;
+use std::fs;
+
+use references::progress::{self, Progress};
+
+#[test]
+fn progress_is_saved_and_loaded_again() {
+    let dir = env::temp_dir().join(format!("references-progress-test-{}", std::process::id()));
+    let path = dir.join("progress");
+
+    let mut saved = Progress::load(&path).unwrap();
+    assert!(saved.lessons.is_empty() && saved.exercises.is_empty());
+    saved.lesson_run("borrowing", 100);
+    saved.lesson_run("borrowing", 160);
+    saved.exercise_started("dangle", 200);
+    saved.attempt("dangle", false, 260);
+    saved.attempt("dangle", true, 500);
+    saved.attempt("dangle", true, 900);
+    saved.attempt("immutable-change", false, 300);
+    saved.save().unwrap();
+
+    let loaded = Progress::load(&path).unwrap();
+    let borrowing = loaded.lesson("borrowing").unwrap();
+    assert_eq!((borrowing.runs, borrowing.last_run), (2, 160));
+
+    let dangle = loaded.exercise("dangle").unwrap();
+    assert_eq!(dangle.attempts, 2);
+    assert_eq!(dangle.passed, Some(500));
+    assert_eq!(dangle.elapsed(10_000), 300);
+
+    let change = loaded.exercise("immutable-change").unwrap();
+    assert_eq!(change.passed, None);
+    assert_eq!(change.elapsed(400), 100);
+
+    assert!(progress::reset(&path).unwrap());
+    assert!(!progress::reset(&path).unwrap());
+
+    fs::write(
+        &path,
+        format!("references progress {}\n", progress::VERSION + 1),
+    )
+    .unwrap();
+    let error = Progress::load(&path).err().unwrap();
+    assert!(error.to_string().contains("newer version"), "{}", error);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quiz_scores_keep_the_best_and_old_files_still_load() {
+    let dir = env::temp_dir().join(format!(
+        "references-quiz-progress-test-{}",
+        std::process::id()
+    ));
+    let path = dir.join("progress");
+
+    let mut saved = Progress::load(&path).unwrap();
+    saved.quiz_taken("borrowing", 3, 4, 100);
+    saved.quiz_taken("borrowing", 4, 4, 200);
+    saved.quiz_taken("borrowing", 1, 4, 300);
+    saved.quiz_taken("dangling-references", 0, 2, 400);
+    saved.save().unwrap();
+
+    let loaded = Progress::load(&path).unwrap();
+    let borrowing = loaded.quiz("borrowing").unwrap();
+    assert_eq!(borrowing.taken, 3);
+    assert_eq!(borrowing.last, (1, 4));
+    assert_eq!(borrowing.best, (4, 4));
+    assert_eq!(borrowing.last_taken, 300);
+    let dangling = loaded.quiz("dangling-references").unwrap();
+    assert_eq!((dangling.taken, dangling.best), (1, (0, 2)));
+    assert!(loaded.quiz("mutable-aliasing").is_none());
+
+    // Version 1 had no quizzes, and reads as having none taken
+    fs::write(
+        &path,
+        "references progress 1\nlesson\tborrowing\t2\t160\nexercise\tdangle\t2\t200\t500\n",
+    )
+    .unwrap();
+    let old = Progress::load(&path).unwrap();
+    assert_eq!(old.lesson("borrowing").unwrap().runs, 2);
+    assert_eq!(old.exercise("dangle").unwrap().passed, Some(500));
+    assert!(old.quizzes.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn durations_read_naturally() {
+    assert_eq!(progress::duration(42), "42s");
+    assert_eq!(progress::duration(125), "2m");
+    assert_eq!(progress::duration(2 * 3600 + 5 * 60), "2h 05m");
+    assert_eq!(progress::duration(3 * 86400), "3 days");
+}
@@ -0,0 +1,54 @@
+// Project: references
+// Author: Greg Folker
+
+// Generated puzzles have to be the same for the same seed, and must only
+// ever fail for borrowing reasons, never because the generator wrote
+// something that is not Rust. Verdicts come back from the cache unchanged
+
+use std::env;
+use std::fs;
+
+use references::cache::{self, Cache};
+use references::catalog;
+use references::puzzle::{self, Rng};
+
+#[test]
+fn the_same_seed_makes_the_same_puzzle() {
+    assert_eq!(puzzle::generate(7).source, puzzle::generate(7).source);
+    assert_ne!(puzzle::generate(7).source, puzzle::generate(8).source);
+
+    let mut rng = Rng::new(0);
+    assert!((0..100).all(|_| rng.below(3) < 3));
+}
+
+#[test]
+fn puzzles_only_fail_with_borrowing_errors_and_verdicts_are_cached() {
+    let dir = env::temp_dir().join(format!("references-puzzle-test-{}", std::process::id()));
+    let cache = Cache::at(&dir, cache::CAPACITY);
+    let (mut compiling, mut failing) = (0, 0);
+
+    for seed in 1..=16 {
+        let puzzle = puzzle::generate(seed);
+        let verdict = puzzle.verdict(&cache).unwrap();
+        assert!(!verdict.cached);
+
+        if verdict.compiles {
+            compiling += 1;
+        } else {
+            failing += 1;
+            for code in &verdict.codes {
+                assert!(catalog::find(code).is_some(), "{}\n{}", code, puzzle.source);
+            }
+        }
+
+        let again = puzzle.verdict(&cache).unwrap();
+        assert!(again.cached);
+        assert_eq!(
+            (again.compiles, &again.codes),
+            (verdict.compiles, &verdict.codes)
+        );
+    }
+
+    assert!(compiling > 0 && failing > 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,54 @@
+// Project: references
+// Author: Greg Folker
+
+// The answers to the quiz come from `rustc` and the recorded output, so
+// every lesson's questions have to be answerable: its own code compiles
+// and prints its snapshot, and each of its errors fails with its code,
+// which is not given away before it is asked for
+
+use references::examples;
+use references::lesson;
+use references::quiz::{self, Kind};
+use references::snapshot;
+
+#[test]
+fn every_question_is_answered_by_rustc_or_the_snapshot() {
+    for lesson in lesson::all() {
+        let questions = quiz::questions(lesson).unwrap();
+        let expected = snapshot::expected(lesson).unwrap();
+
+        assert_eq!(questions[0].kind, Kind::Compiles);
+        assert!(questions[0].check("yes").unwrap().correct, "{}", lesson.id);
+        assert!(!questions[0].check("no").unwrap().correct, "{}", lesson.id);
+
+        assert_eq!(questions[1].kind, Kind::Prints);
+        assert!(questions[1].check(expected).unwrap().correct);
+        assert!(!questions[1].check("Hello, world!").unwrap().correct);
+
+        let examples: Vec<_> = examples::all()
+            .unwrap()
+            .into_iter()
+            .filter(|e| lesson::for_example(e).map(|l| l.id) == Some(lesson.id))
+            .collect();
+        assert_eq!(questions.len(), 2 + 2 * examples.len());
+
+        for (pair, example) in questions[2..].chunks(2).zip(&examples) {
+            assert_eq!(pair[0].kind, Kind::Compiles);
+            let verdict = pair[0].check("no").unwrap();
+            assert!(verdict.correct, "{}", example.id);
+            // The code is the answer to the next question
+            assert!(
+                !verdict.answer.contains(&example.code),
+                "{}",
+                verdict.answer
+            );
+            assert!(!pair[0].snippet.contains("@compile_fail"));
+
+            assert_eq!(pair[1].kind, Kind::ErrorCode);
+            assert!(pair[1].prompt().unwrap().starts_with("It does not compile"));
+            assert!(pair[1].check(&example.code).unwrap().correct);
+            assert!(pair[1].check(&example.code[1..]).unwrap().correct);
+            assert!(!pair[1].check("E0000").unwrap().correct);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Project: references
+// Author: Greg Folker
+
+// A program run in the sandbox cannot run forever, flood its output, use
+// all the memory, leave processes running or read secrets from the
+// environment
+
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use references::compile;
+use references::sandbox::{self, Exit, Limits, Sandbox};
+
+const MISBEHAVING: &str = r#"
+use std::env;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    match env::args().nth(1).as_deref() {
+        Some("spin") => loop {},
+        Some("sleep") => thread::sleep(Duration::from_secs(60)),
+        Some("flood") => loop {
+            println!("Hello, world");
+        },
+        Some("linger") => {
+            Command::new(env::current_exe().unwrap())
+                .arg("sleep")
+                .spawn()
+                .unwrap();
+        }
+        Some("allocate") => {
+            let memory = vec![1u8; 2 << 30];
+            println!("{}", memory.len());
+        }
+        _ => println!("{:?}", env::var("REFERENCES_SANDBOX_SECRET").ok()),
+    }
+}
+"#;
+
+#[test]
+fn runs_are_kept_within_their_limits() {
+    let dir = env::temp_dir().join(format!("references-sandbox-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let outcome = compile::compile_in(&dir, "misbehaving", MISBEHAVING).unwrap();
+    assert!(outcome.success, "{}", outcome.stderr);
+
+    let limits = Limits {
+        cpu_seconds: 1,
+        wall_clock: Duration::from_secs(3),
+        output_bytes: 1000,
+        ..sandbox::PROGRAM
+    };
+    let sandbox = Sandbox::new(limits, &[]);
+    let run = |mode: &str| sandbox.run(dir.join("misbehaving"), [mode], &dir).unwrap();
+
+    let spin = run("spin");
+    assert!(matches!(spin.exit, Exit::Signal(_)), "{:?}", spin.exit);
+
+    let started = Instant::now();
+    assert_eq!(run("sleep").exit, Exit::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    // The child it leaves sleeping would keep the output open for a minute
+    let started = Instant::now();
+    assert!(run("linger").success());
+    assert!(started.elapsed() < Duration::from_secs(3));
+
+    let flood = run("flood");
+    assert!(flood.truncated);
+    assert_eq!(flood.stdout.len(), 1000);
+
+    assert!(!run("allocate").success());
+
+    env::set_var("REFERENCES_SANDBOX_SECRET", "hunter2");
+    let secret = run("secret");
+    assert!(secret.success());
+    assert_eq!(secret.stdout, "None\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
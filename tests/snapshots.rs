@@ -0,0 +1,59 @@
+// Project: references
+// Author: Greg Folker
+
+// Every lesson has to keep printing what is recorded for it in
+// `src/snapshots`. Run with `BLESS=1` to record the current output instead.
+// What is built into the binary has to be what is recorded
+
+use std::env;
+use std::path::Path;
+
+use references::lesson;
+use references::snapshot::{self, Status};
+
+#[test]
+fn lessons_match_snapshots() {
+    let exe = Path::new(env!("CARGO_BIN_EXE_references"));
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for lesson in lesson::all() {
+        let output = snapshot::capture(exe, lesson).unwrap();
+
+        if bless {
+            snapshot::bless(lesson, &output).unwrap();
+            continue;
+        }
+
+        match snapshot::compare(lesson, &output).unwrap() {
+            Status::Matches => {}
+            Status::Missing => failures.push(format!(
+                "{}: no recorded output in {}",
+                lesson.id,
+                snapshot::path(lesson).display()
+            )),
+            Status::Differs(diff) => failures.push(format!(
+                "{}: output changed (- recorded, + actual)\n{}",
+                lesson.id, diff
+            )),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "\n{}\nrun `BLESS=1 cargo test` if the new output is correct",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn built_in_snapshots_are_the_recorded_ones() {
+    for lesson in lesson::all() {
+        assert_eq!(
+            snapshot::expected(lesson).map(String::from),
+            snapshot::recorded(lesson).unwrap(),
+            "{} is not built in as recorded, see `RECORDED` in src/snapshot.rs",
+            lesson.id
+        );
+    }
+}
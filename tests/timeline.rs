@@ -0,0 +1,48 @@
+// Project: references
+// Author: Greg Folker
+
+// The timeline of `shared-and-mutable` is the one the lesson is built
+// around: `r1` and `r2` die at the `println!` that last uses them, and the
+// commented-out `r3` is flagged as overlapping them with E0502
+
+use references::lesson;
+use references::timeline;
+
+// The character in `row` under the column headed `name`
+fn cell(header: &str, row: &str, name: &str) -> char {
+    let column = header
+        .find(&format!(" {} ", name))
+        .unwrap_or_else(|| panic!("no column for {} in {:?}", name, header))
+        + 1;
+    row.chars().nth(column).unwrap_or(' ')
+}
+
+#[test]
+fn shared_borrows_die_at_their_last_use() {
+    let lesson = lesson::find("shared-and-mutable").unwrap();
+    let drawn = timeline::render(lesson).unwrap();
+    let lines: Vec<&str> = drawn.lines().collect();
+    let header = format!("{} ", lines[0]);
+
+    let last_use = lines
+        .iter()
+        .position(|line| line.contains("println!(\"r1={} and r2={}\""))
+        .unwrap();
+    for name in ["r1", "r2"] {
+        assert_eq!(cell(&header, lines[last_use], name), '*', "{}", drawn);
+        for line in &lines[last_use + 1..] {
+            if line.trim().is_empty() {
+                break;
+            }
+            assert_eq!(cell(&header, line, name), ' ', "{}", drawn);
+        }
+    }
+
+    let hypothetical = lines
+        .iter()
+        .find(|line| line.contains("// let r3 = &mut s;"))
+        .unwrap();
+    assert_eq!(cell(&header, hypothetical, "r3?"), '!', "{}", drawn);
+    assert!(hypothetical.contains("overlaps r1, r2"), "{}", drawn);
+    assert!(hypothetical.contains("(E0502)"), "{}", drawn);
+}
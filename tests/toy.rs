@@ -0,0 +1,140 @@
+// Project: references
+// Author: Greg Folker
+
+// The toy versions of the lessons have to behave like the real ones: the
+// lessons print what their snapshots say, and the commented-out errors
+// fail with the same code `rustc` gives them. A program that recurses
+// forever is stopped rather than taking the tool down with it, and a
+// reference to a reference keeps what the first one borrows borrowed
+
+use references::examples;
+use references::lesson;
+use references::snapshot;
+use references::toy::step::State;
+use references::toy::{self, Program};
+
+fn program(name: &str) -> Program {
+    let source = toy::find(name).unwrap_or_else(|| panic!("no toy program named '{}'", name));
+    Program::parse(name, source).unwrap_or_else(|e| panic!("\n{}", e.render(name, source)))
+}
+
+#[test]
+fn lessons_print_their_snapshots() {
+    for lesson in lesson::all() {
+        let program = program(lesson.id);
+        let report = program.check();
+        let errors: Vec<String> = report
+            .diagnostics
+            .iter()
+            .map(|d| program.render(d))
+            .collect();
+        assert!(report.is_ok(), "\n{}", errors.join("\n"));
+
+        let expected = snapshot::expected(lesson).unwrap_or_default();
+        assert_eq!(
+            program.run().unwrap(),
+            expected,
+            "output of '{}'",
+            lesson.id
+        );
+    }
+}
+
+#[test]
+fn examples_fail_like_rustc() {
+    for example in examples::all().unwrap() {
+        let report = program(&example.id).check();
+        let codes: Vec<&str> = report.diagnostics.iter().filter_map(|d| d.code).collect();
+
+        assert_eq!(
+            codes,
+            [example.code.as_str()],
+            "errors for '{}'",
+            example.id
+        );
+    }
+}
+
+#[test]
+fn stepping_ends_borrows_after_their_last_use() {
+    let steps = program("shared-and-mutable").steps().unwrap();
+    let loans = |line: usize| {
+        let step = steps.iter().find(|step| step.line == line).unwrap();
+        step.variables
+            .iter()
+            .filter(|v| {
+                matches!(
+                    v.state,
+                    State::Ref {
+                        last_use: Some(_),
+                        ..
+                    }
+                )
+            })
+            .map(|v| v.name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(loans(7), ["r1", "r2"]);
+    assert!(loans(9).is_empty());
+    assert_eq!(loans(11), ["r3"]);
+}
+
+#[test]
+fn stepping_releases_borrows_passed_to_a_call() {
+    let steps = program("mutable-references").steps().unwrap();
+    let step = steps
+        .iter()
+        .find(|step| step.code == "change(&mut s);")
+        .unwrap();
+
+    assert_eq!(step.released, ["&mut s"]);
+    match &step.variables[..] {
+        [v] => assert!(
+            matches!(&v.state, State::Owned { text, borrowed_by } if text == "Hello, world" && borrowed_by.is_empty())
+        ),
+        _ => panic!("expected only `s` to be in scope"),
+    }
+}
+
+#[test]
+fn a_reference_keeps_the_owner_it_borrowed_from() {
+    let source = "fn main() {\n    let s = String::from(\"Hello\");\n    let r = &s;\n    let q = r;\n    println!(\"{}\", q);\n    let t = s;\n    println!(\"{}\", t);\n}\n";
+    let program = Program::parse("moved", source)
+        .unwrap_or_else(|e| panic!("\n{}", e.render("moved", source)));
+    let steps = program.steps().unwrap();
+    let last = steps.iter().find(|step| step.line == 7).unwrap();
+
+    for name in ["r", "q"] {
+        let variable = last.variables.iter().find(|v| v.name == name).unwrap();
+        assert!(
+            matches!(&variable.state, State::Ref { owner, .. } if owner == "s"),
+            "`{}` should still be shown borrowing from `s`",
+            name
+        );
+    }
+}
+
+#[test]
+fn endless_recursion_is_stopped_with_an_error() {
+    let source = "fn main() {\n    main();\n}\n";
+    let program = Program::parse("recursion", source)
+        .unwrap_or_else(|e| panic!("\n{}", e.render("recursion", source)));
+    assert!(program.check().is_ok());
+
+    let error = program.run().unwrap_err();
+    assert!(error.contains("recursion limit"), "{}", error);
+    let error = toy::step::steps(&program).err().unwrap();
+    assert!(error.contains("recursion limit"), "{}", error);
+}
+
+#[test]
+fn a_reference_to_a_reference_keeps_the_first_borrow() {
+    let source = "fn main() {\n    let mut s = String::from(\"Hello\");\n    let r = &mut s;\n    let rr = &r;\n    println!(\"{}\", s);\n    println!(\"{}\", rr);\n}\n";
+    let program = Program::parse("reborrow", source)
+        .unwrap_or_else(|e| panic!("\n{}", e.render("reborrow", source)));
+    let report = program.check();
+    let codes: Vec<&str> = report.diagnostics.iter().filter_map(|d| d.code).collect();
+
+    assert_eq!(codes, ["E0502"]);
+}
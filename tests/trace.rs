@@ -0,0 +1,59 @@
+// Project: references
+// Author: Greg Folker
+
+// The traced lessons have to report what happens to their `String` in the
+// order it happens: created, borrowed or moved, and dropped only once the
+// scope that owns it ends
+
+use std::process::Command;
+
+use references::progress;
+
+// The `[trace]` lines `run --trace` prints for `lesson`, without the prefix
+fn trace(lesson: &str) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_references"))
+        .args(["run", "--trace", lesson])
+        .env(progress::DISABLE_VARIABLE, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", lesson);
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("[trace] "))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn a_borrowed_string_is_dropped_by_its_owner() {
+    assert_eq!(
+        trace("mutable-references"),
+        [
+            "{ mutable_references",
+            "  create `s` = \"Hello\"",
+            "  borrow `s` as &mut String for `some_string`",
+            "  { change",
+            "    `some_string` changed the borrowed value to \"Hello, world\"",
+            "  } change",
+            "  drop `s` (\"Hello, world\"), freeing its heap memory",
+            "} mutable_references",
+        ]
+    );
+}
+
+#[test]
+fn a_moved_string_is_dropped_by_its_new_owner() {
+    assert_eq!(
+        trace("dangling-references"),
+        [
+            "{ dangling_references",
+            "  { no_dangle",
+            "    create `s` = \"Hello\"",
+            "    move `s` to `reference_to_something`",
+            "  } no_dangle",
+            "  drop `reference_to_something` (\"Hello\"), freeing its heap memory",
+            "} dangling_references",
+        ]
+    );
+}
@@ -0,0 +1,102 @@
+// Project: references
+// Author: Greg Folker
+
+// A watched file is reported once when first read and again only after
+// its contents change. Grading a watched save counts neither as an
+// attempt nor towards a hint, while `check` counts as both
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use references::hints;
+use references::progress::{self, Progress};
+use references::watch::Watcher;
+
+#[test]
+fn changes_are_reported_once() {
+    let dir = env::temp_dir().join(format!("references-watch-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("dangle.rs");
+
+    let mut watcher = Watcher::new(&path);
+    assert_eq!(watcher.poll().unwrap(), None);
+
+    fs::write(&path, "fn main() {}\n").unwrap();
+    assert_eq!(watcher.poll().unwrap().as_deref(), Some("fn main() {}\n"));
+    assert_eq!(watcher.poll().unwrap(), None);
+
+    fs::write(&path, "fn main() {}\n").unwrap();
+    assert_eq!(watcher.poll().unwrap(), None);
+
+    fs::write(&path, "fn main() { }\n").unwrap();
+    assert_eq!(watcher.wait().unwrap(), "fn main() { }\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn watched_saves_are_not_attempts() {
+    let dir = env::temp_dir().join(format!("references-watched-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let (data, workspace) = (dir.join("data"), dir.join("exercises"));
+    let file = workspace.join("immutable-change.rs");
+    let references = || {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_references"));
+        command
+            .env(progress::DIR_VARIABLE, &data)
+            .env("REFERENCES_STUDENT", "watcher")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        command
+    };
+    let counts = || {
+        let attempts = Progress::load(&data.join("progress"))
+            .unwrap()
+            .exercise("immutable-change")
+            .map_or(0, |exercise| exercise.attempts);
+        let failures = hints::Log::load(&workspace)
+            .unwrap()
+            .record("watcher", "immutable-change")
+            .failures;
+        (attempts, failures)
+    };
+
+    let written = references()
+        .args(["exercise", "immutable-change"])
+        .arg(&workspace)
+        .status()
+        .unwrap();
+    assert!(written.success());
+
+    // The file is graded as soon as it is first read
+    let mut watch = references().arg("watch").arg(&file).spawn().unwrap();
+    let lines = BufReader::new(watch.stdout.take().unwrap()).lines();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in lines.map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    loop {
+        let line = receiver.recv_timeout(Duration::from_secs(120)).unwrap();
+        if line.contains("checks pass") {
+            break;
+        }
+    }
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+    assert_eq!(counts(), (0, 0));
+
+    let checked = references().arg("check").arg(&file).status().unwrap();
+    assert!(!checked.success());
+    assert_eq!(counts(), (1, 1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+// Project: references
+// Author: Greg Folker
+
+// `whatif` has to show the code it uncomments, from the line the lesson
+// file has it on, and what `rustc` says about it. Without an example it
+// lists them all, and an example that does not exist is an error
+
+use std::process::{Command, Output};
+
+use references::examples;
+use references::progress;
+use references::source;
+
+fn whatif(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_references"))
+        .arg("whatif")
+        .args(args)
+        .env(progress::DISABLE_VARIABLE, "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn uncommented_code_gets_the_error_rustc_gives() {
+    let output = whatif(&["double-mutable-borrow"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+
+    let line: usize = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Uncommenting src/references.rs line "))
+        .and_then(|rest| rest.trim_end_matches(':').parse().ok())
+        .unwrap_or_else(|| panic!("no line number in\n{}", stdout));
+    let commented = source::REFERENCES.lines().nth(line - 1).unwrap();
+    assert_eq!(commented.trim(), "// let r2 = &mut s;");
+
+    assert!(stdout.contains("\n    let r2 = &mut s;\n"), "{}", stdout);
+    assert!(stdout.contains("error[E0499]"), "{}", stdout);
+    assert!(!stdout.contains("note: the lesson expects"), "{}", stdout);
+}
+
+#[test]
+fn every_example_is_listed_and_others_are_refused() {
+    let output = whatif(&[]);
+    assert!(output.status.success());
+    let listed = String::from_utf8_lossy(&output.stdout);
+    for example in examples::all().unwrap() {
+        assert!(
+            listed.lines().any(|line| line.starts_with(&example.id)),
+            "{} is not listed in\n{}",
+            example.id,
+            listed
+        );
+    }
+
+    let unknown = whatif(&["no-such-example"]);
+    assert_eq!(unknown.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown example"));
+}